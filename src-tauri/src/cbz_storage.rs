@@ -0,0 +1,234 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    extensions::PathIsImg,
+    library, sidecar,
+    types::{Comic, SidecarFormat},
+};
+
+/// Name of the metadata file, both in comic folders and in CBZ archives
+pub const METADATA_FILENAME: &str = "metadata.json";
+
+/// Whether `path` is a CBZ archive, judged by its extension only
+pub fn is_cbz_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("cbz"))
+}
+
+/// The archive that `StorageMode::Cbz` stores a comic in, which is `comic_download_dir` with `.cbz` appended
+pub fn cbz_path_of(comic_download_dir: &Path) -> PathBuf {
+    let mut cbz_path = OsString::from(comic_download_dir.as_os_str());
+    cbz_path.push(".cbz");
+    PathBuf::from(cbz_path)
+}
+
+pub fn open_zip_archive(cbz_path: &Path) -> anyhow::Result<ZipArchive<File>> {
+    let file = File::open(cbz_path).context(format!("Failed to open `{}`", cbz_path.display()))?;
+    let zip_archive =
+        ZipArchive::new(file).context(format!("Failed to read `{}` as zip", cbz_path.display()))?;
+    Ok(zip_archive)
+}
+
+/// Whether `cbz_path` is a comic downloaded by this app, rather than any other CBZ archive
+pub fn contains_metadata(cbz_path: &Path) -> bool {
+    open_zip_archive(cbz_path).is_ok_and(|zip_archive| {
+        zip_archive
+            .file_names()
+            .any(|name| name == METADATA_FILENAME)
+    })
+}
+
+/// Read `metadata.json` in `cbz_path`
+pub fn read_metadata(cbz_path: &Path) -> anyhow::Result<String> {
+    let mut zip_archive = open_zip_archive(cbz_path)?;
+    let mut zip_file = zip_archive.by_name(METADATA_FILENAME).context(format!(
+        "Failed to find `{METADATA_FILENAME}` in `{}`",
+        cbz_path.display()
+    ))?;
+    let mut metadata_string = String::new();
+    zip_file
+        .read_to_string(&mut metadata_string)
+        .context(format!(
+            "Failed to read `{METADATA_FILENAME}` in `{}`",
+            cbz_path.display()
+        ))?;
+    Ok(metadata_string)
+}
+
+/// Pack the pages in `pages_dir` into `cbz_path`, along with `metadata.json`, `ComicInfo.xml`,
+/// the other sidecars in `sidecar_formats` and the checksum manifest of the pages
+///
+/// The archive is written next to `cbz_path` first and then renamed, so `cbz_path` is never half written
+pub fn pack(
+    comic: &Comic,
+    pages_dir: &Path,
+    cbz_path: &Path,
+    sidecar_formats: &[SidecarFormat],
+) -> anyhow::Result<()> {
+    let comic_title = &comic.title;
+
+    let mut page_paths: Vec<PathBuf> = std::fs::read_dir(pages_dir)
+        .context(format!(
+            "Failed to read directory `{}`",
+            pages_dir.display()
+        ))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_img())
+        .collect();
+    page_paths.sort();
+
+    let temp_cbz_path = temp_cbz_path_of(cbz_path);
+    let zip_file = File::create(&temp_cbz_path)
+        .context(format!("Failed to create `{}`", temp_cbz_path.display()))?;
+    let mut zip_writer = ZipWriter::new(zip_file);
+
+    write_metadata_entries(&mut zip_writer, comic, sidecar_formats)?;
+    let mut hashes = BTreeMap::new();
+    for page_path in page_paths {
+        let Some(filename) = page_path.file_name() else {
+            continue;
+        };
+        let filename = filename.to_string_lossy().to_string();
+        let data = std::fs::read(&page_path)
+            .context(format!("Failed to read `{}`", page_path.display()))?;
+        // The pages are already compressed images, deflating them again only costs time
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        zip_writer.start_file(&filename, options).context(format!(
+            "`{comic_title}` failed to create `{filename}` in `{}`",
+            temp_cbz_path.display()
+        ))?;
+        zip_writer.write_all(&data).context(format!(
+            "`{comic_title}` failed to write `{}` to `{}`",
+            page_path.display(),
+            temp_cbz_path.display()
+        ))?;
+        hashes.insert(filename, library::sha256_hex(&data));
+    }
+    zip_writer
+        .start_file(library::MANIFEST_FILENAME, SimpleFileOptions::default())
+        .context(format!(
+            "`{comic_title}` failed to create `{}`",
+            library::MANIFEST_FILENAME
+        ))?;
+    zip_writer
+        .write_all(library::format_manifest(&hashes).as_bytes())
+        .context(format!(
+            "`{comic_title}` failed to write `{}`",
+            library::MANIFEST_FILENAME
+        ))?;
+    zip_writer.finish().context(format!(
+        "`{comic_title}` failed to close `{}`",
+        temp_cbz_path.display()
+    ))?;
+
+    std::fs::rename(&temp_cbz_path, cbz_path).context(format!(
+        "Failed to rename `{}` to `{}`",
+        temp_cbz_path.display(),
+        cbz_path.display()
+    ))?;
+
+    Ok(())
+}
+
+/// Replace `metadata.json` and the sidecars in `cbz_path` with ones generated from `comic`,
+/// only the sidecars that are already in the archive are written again
+pub fn rewrite_metadata(comic: &Comic, cbz_path: &Path) -> anyhow::Result<()> {
+    let comic_title = &comic.title;
+
+    let mut zip_archive = open_zip_archive(cbz_path)?;
+    let sidecar_formats: Vec<SidecarFormat> = SidecarFormat::ALL
+        .into_iter()
+        .filter(|format| {
+            zip_archive
+                .file_names()
+                .any(|name| name == format.filename())
+        })
+        .collect();
+
+    let temp_cbz_path = temp_cbz_path_of(cbz_path);
+    let zip_file = File::create(&temp_cbz_path)
+        .context(format!("Failed to create `{}`", temp_cbz_path.display()))?;
+    let mut zip_writer = ZipWriter::new(zip_file);
+
+    write_metadata_entries(&mut zip_writer, comic, &sidecar_formats)?;
+    for i in 0..zip_archive.len() {
+        let zip_file = zip_archive.by_index_raw(i).context(format!(
+            "Failed to read entry `{i}` of `{}`",
+            cbz_path.display()
+        ))?;
+        let name = zip_file.name();
+        let is_metadata_entry = name == METADATA_FILENAME
+            || SidecarFormat::ALL
+                .iter()
+                .any(|format| format.filename() == name);
+        if is_metadata_entry {
+            continue;
+        }
+        // Copy the pages as they are, without decompressing and compressing them again
+        zip_writer.raw_copy_file(zip_file).context(format!(
+            "`{comic_title}` failed to copy entry `{i}` to `{}`",
+            temp_cbz_path.display()
+        ))?;
+    }
+    zip_writer.finish().context(format!(
+        "`{comic_title}` failed to close `{}`",
+        temp_cbz_path.display()
+    ))?;
+    drop(zip_archive);
+
+    std::fs::rename(&temp_cbz_path, cbz_path).context(format!(
+        "Failed to rename `{}` to `{}`",
+        temp_cbz_path.display(),
+        cbz_path.display()
+    ))?;
+
+    Ok(())
+}
+
+/// `ComicInfo.xml` is always written, because it's what other readers look for in a CBZ archive
+fn write_metadata_entries(
+    zip_writer: &mut ZipWriter<File>,
+    comic: &Comic,
+    sidecar_formats: &[SidecarFormat],
+) -> anyhow::Result<()> {
+    let comic_title = &comic.title;
+
+    let mut entries = vec![(
+        SidecarFormat::ComicInfoXml.filename(),
+        sidecar::render_sidecar(comic, SidecarFormat::ComicInfoXml)?,
+    )];
+    for format in sidecar_formats {
+        if *format == SidecarFormat::ComicInfoXml {
+            continue;
+        }
+        entries.push((format.filename(), sidecar::render_sidecar(comic, *format)?));
+    }
+    entries.push((METADATA_FILENAME, comic.to_metadata_json()?));
+
+    for (filename, content) in entries {
+        zip_writer
+            .start_file(filename, SimpleFileOptions::default())
+            .context(format!("`{comic_title}` failed to create `{filename}`"))?;
+        zip_writer
+            .write_all(content.as_bytes())
+            .context(format!("`{comic_title}` failed to write `{filename}`"))?;
+    }
+
+    Ok(())
+}
+
+fn temp_cbz_path_of(cbz_path: &Path) -> PathBuf {
+    let mut temp_cbz_path = OsString::from(cbz_path.as_os_str());
+    temp_cbz_path.push(".part");
+    PathBuf::from(temp_cbz_path)
+}
@@ -1,22 +1,24 @@
 use std::path::PathBuf;
 
-use anyhow::{anyhow, Context};
-use indexmap::IndexMap;
+use anyhow::Context;
 use parking_lot::RwLock;
 use tauri::{AppHandle, State};
 use tauri_plugin_opener::OpenerExt;
-use walkdir::WalkDir;
 
 use crate::{
     config::Config,
     download_manager::DownloadManager,
     errors::{CommandError, CommandResult},
-    export,
+    export::{self, ExportBatchReport, ExportCancellations, ExportStatusReport, SyncReport},
     extensions::AnyhowErrorToStringChain,
     hitomi::Suggestion,
     hitomi_client::HitomiClient,
-    logger,
-    types::{Comic, SearchResult},
+    library::{
+        self, AuditReport, DuplicateGroup, ImportMode, ImportReport, LibraryStats, LibraryWatcher,
+        PageStoreGcReport, RefreshReport, TrashReport, TrashedComic, VerifyReport,
+    },
+    logger, sidecar,
+    types::{Comic, DeviceProfile, ExportFormat, SearchResult, SidecarFormat},
 };
 
 #[tauri::command]
@@ -40,6 +42,7 @@ pub fn get_config(config: State<RwLock<Config>>) -> Config {
 pub fn save_config(
     app: AppHandle,
     hitomi_client: State<HitomiClient>,
+    library_watcher: State<LibraryWatcher>,
     config_state: State<RwLock<Config>>,
     config: Config,
 ) -> CommandResult<()> {
@@ -50,6 +53,8 @@ pub fn save_config(
             || config_state.proxy_port != config.proxy_port
     };
 
+    let download_dir_changed = config_state.read().download_dir != config.download_dir;
+
     let enable_file_logger = config.enable_file_logger;
     let enable_file_logger_changed = config_state
         .read()
@@ -70,6 +75,15 @@ pub fn save_config(
         hitomi_client.reload_client();
     }
 
+    // The config is already saved, so a watcher that fails to restart is only logged instead of failing the command
+    if download_dir_changed {
+        if let Err(err) = library_watcher.restart() {
+            let err_title = "Failed to restart the library watcher";
+            let string_chain = err.to_string_chain();
+            tracing::error!(err_title, message = string_chain);
+        }
+    }
+
     if enable_file_logger_changed {
         if enable_file_logger {
             logger::reload_file_logger()
@@ -190,122 +204,198 @@ pub fn cancel_download_task(
 #[allow(clippy::needless_pass_by_value)]
 pub fn get_downloaded_comics(config: State<RwLock<Config>>) -> Vec<Comic> {
     let download_dir = config.read().download_dir.clone();
-    // Traverse the download directory to get the path and modification time of all metadata files
-    let mut metadata_path_with_modify_time = Vec::new();
-    for entry in WalkDir::new(&download_dir)
+    let downloaded_comics = library::get_downloaded_comics(&download_dir);
+    tracing::debug!("get downloaded comics success");
+    downloaded_comics
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[tauri::command(async)]
+#[specta::specta]
+pub fn delete_downloaded_comics(
+    app: AppHandle,
+    config: State<RwLock<Config>>,
+    ids: Vec<i32>,
+    delete_exports: bool,
+) -> TrashReport {
+    let download_dir = config.read().download_dir.clone();
+    // Every version of a comic is deleted, not only the one that is shown
+    let comics_to_delete: Vec<Comic> = library::get_all_downloaded_comics(&download_dir)
         .into_iter()
-        .filter_map(Result::ok)
-    {
-        let path = entry.path();
-        if path.is_dir() {
-            continue;
-        }
-        if entry.file_name() != "metadata.json" {
-            continue;
-        }
-        // now the entry is the metadata.json file
-        let metadata = match path
-            .metadata()
-            .map_err(anyhow::Error::from)
-            .context(format!(
-                "Failed to get file metadata of `{}`",
-                path.display()
-            )) {
-            Ok(metadata) => metadata,
-            Err(err) => {
-                let err_title = "An error occurred while getting downloaded comics, skipped";
-                let string_chain = err.to_string_chain();
-                tracing::error!(err_title, message = string_chain);
-                continue;
-            }
-        };
+        .filter(|comic| ids.contains(&comic.id))
+        .collect();
 
-        let modify_time = match metadata
-            .modified()
-            .map_err(anyhow::Error::from)
-            .context(format!(
-                "Failed to get file modification time of `{}`",
-                path.display()
-            )) {
-            Ok(modify_time) => modify_time,
-            Err(err) => {
-                let err_title = "An error occurred while getting downloaded comics, skipped";
-                let string_chain = err.to_string_chain();
-                tracing::error!(err_title, message = string_chain);
-                continue;
-            }
-        };
+    let trash_report = library::move_comics_to_trash(&app, &comics_to_delete, delete_exports);
+    tracing::debug!("Deleted comics with IDs `{ids:?}`");
+    trash_report
+}
 
-        metadata_path_with_modify_time.push((path.to_path_buf(), modify_time));
+#[allow(clippy::needless_pass_by_value)]
+#[tauri::command(async)]
+#[specta::specta]
+pub fn get_trashed_comics(app: AppHandle) -> CommandResult<Vec<TrashedComic>> {
+    let trashed_comics = library::get_trashed_comics(&app)
+        .map_err(|err| CommandError::from("Failed to get trashed comics", err))?;
+    tracing::debug!("get trashed comics success");
+    Ok(trashed_comics)
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[tauri::command(async)]
+#[specta::specta]
+pub fn restore_trashed_comics(app: AppHandle, trash_ids: Vec<String>) -> CommandResult<Vec<Comic>> {
+    let mut restored_comics = Vec::new();
+    for trash_id in &trash_ids {
+        let comic = library::restore_trashed_comic(&app, trash_id).map_err(|err| {
+            let err_title = format!("Failed to restore trashed comic `{trash_id}`");
+            CommandError::from(&err_title, err)
+        })?;
+        restored_comics.push(comic);
     }
-    // Sort by file modification time, with the newest at the front
-    metadata_path_with_modify_time.sort_by(|(_, a), (_, b)| b.cmp(a));
-    // Create Comic from metadata file
-    let mut downloaded_comics = Vec::new();
-    for (metadata_path, _) in metadata_path_with_modify_time {
-        match Comic::from_metadata(&metadata_path).context(format!(
-            "Failed to create Comic from metadata `{}`",
-            metadata_path.display()
-        )) {
-            Ok(comic) => downloaded_comics.push(comic),
-            Err(err) => {
-                let err_title = "An error occurred while getting downloaded comics, skipped";
-                let string_chain = err.to_string_chain();
-                tracing::error!(err_title, message = string_chain);
-            }
-        }
+    tracing::debug!("Restored trashed comics `{trash_ids:?}` successfully");
+    Ok(restored_comics)
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[tauri::command(async)]
+#[specta::specta]
+pub fn delete_trashed_comics(app: AppHandle, trash_ids: Vec<String>) -> CommandResult<()> {
+    for trash_id in &trash_ids {
+        library::delete_trashed_comic(&app, trash_id).map_err(|err| {
+            let err_title = format!("Failed to permanently delete trashed comic `{trash_id}`");
+            CommandError::from(&err_title, err)
+        })?;
     }
+    tracing::debug!("Permanently deleted trashed comics `{trash_ids:?}` successfully");
+    Ok(())
+}
 
-    tracing::debug!("get downloaded comics success");
+#[tauri::command(async)]
+#[specta::specta]
+#[allow(clippy::needless_pass_by_value)]
+pub fn get_library_stats(config: State<RwLock<Config>>, top_n: usize) -> LibraryStats {
+    let download_dir = config.read().download_dir.clone();
+    let library_stats = library::get_library_stats(&download_dir, top_n);
+    tracing::debug!("get library stats success");
+    library_stats
+}
 
-    // Group comics by their ID to facilitate deduplication
-    let mut comics_by_id: IndexMap<i32, Vec<Comic>> = IndexMap::new();
-    for comic in downloaded_comics {
-        comics_by_id.entry(comic.id).or_default().push(comic);
-    }
+#[tauri::command(async)]
+#[specta::specta]
+#[allow(clippy::needless_pass_by_value)]
+pub fn audit_library(config: State<RwLock<Config>>, decode_images: bool) -> AuditReport {
+    let download_dir = config.read().download_dir.clone();
+    let audit_report = library::audit_library(&download_dir, decode_images);
+    tracing::debug!("audit library success");
+    audit_report
+}
 
-    let mut unique_comics = Vec::new();
-    for (_comic_id, mut comics) in comics_by_id {
-        // The download directories for all comics with the same ID, which may have multiple versions, so we need to deduplicate
-        let comic_download_dirs: Vec<&PathBuf> = comics
-            .iter()
-            .filter_map(|comic| comic.comic_download_dir.as_ref())
-            .collect();
+#[tauri::command(async)]
+#[specta::specta]
+#[allow(clippy::needless_pass_by_value)]
+pub fn find_duplicate_comics(
+    app: AppHandle,
+    min_similarity: f64,
+) -> CommandResult<Vec<DuplicateGroup>> {
+    let duplicate_groups = library::find_duplicate_comics(&app, min_similarity)
+        .map_err(|err| CommandError::from("find duplicate comics failed", err))?;
+    tracing::debug!("find duplicate comics success");
+    Ok(duplicate_groups)
+}
 
-        if comic_download_dirs.is_empty() {
-            // This situation should not actually happen, because the comic metadata file should always have a download directory
-            continue;
-        }
+#[tauri::command(async)]
+#[specta::specta]
+#[allow(clippy::needless_pass_by_value)]
+pub fn collect_page_store_garbage(
+    config: State<RwLock<Config>>,
+) -> CommandResult<PageStoreGcReport> {
+    let download_dir = config.read().download_dir.clone();
+    let gc_report = library::collect_page_store_garbage(&download_dir)
+        .map_err(|err| CommandError::from("collect page store garbage failed", err))?;
+    tracing::debug!("collect page store garbage success");
+    Ok(gc_report)
+}
 
-        // Choose the first one as the retained comic
-        let chosen_download_dir = comic_download_dirs[0];
-
-        if comics.len() > 1 {
-            let dir_paths_string = comic_download_dirs
-                .iter()
-                .map(|path| format!("`{}`", path.display()))
-                .collect::<Vec<String>>()
-                .join(", ");
-            // If there are duplicate comics, report an error
-            let comic_title = &comics[0].title;
-            let err_title = "An error occurred while getting downloaded comics";
-            let string_chain = anyhow!("All version paths: [{dir_paths_string}]")
-                .context(format!(
-                    "To proceed, temporarily selected only the version '{}' from the multiple versions found",
-                    chosen_download_dir.display()
-                ))
-                .context(format!(
-                    "Comic `{comic_title}` has multiple versions in the download directory. Please handle this manually, keeping only one",
-                ))
-                .to_string_chain();
-            tracing::error!(err_title, message = string_chain);
+#[tauri::command(async)]
+#[specta::specta]
+#[allow(clippy::needless_pass_by_value)]
+pub fn verify_library(config: State<RwLock<Config>>, ids: Option<Vec<i32>>) -> VerifyReport {
+    let download_dir = config.read().download_dir.clone();
+    let verify_report = library::verify_comics(&download_dir, ids.as_deref());
+    tracing::debug!("verify library success");
+    verify_report
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+#[allow(clippy::needless_pass_by_value)]
+pub fn verify_exported_cbzs(
+    app: AppHandle,
+    config: State<RwLock<Config>>,
+    ids: Option<Vec<i32>>,
+) -> VerifyReport {
+    let download_dir = config.read().download_dir.clone();
+    let verify_report = library::verify_exported_cbzs(&app, &download_dir, ids.as_deref());
+    tracing::debug!("verify exported cbzs success");
+    verify_report
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn import_comics(
+    app: AppHandle,
+    source_dir: PathBuf,
+    mode: ImportMode,
+) -> CommandResult<ImportReport> {
+    let import_report = library::import_comics(&app, &source_dir, mode)
+        .await
+        .map_err(|err| {
+            let err_title = format!("Failed to import comics from `{}`", source_dir.display());
+            CommandError::from(&err_title, err)
+        })?;
+    tracing::debug!(
+        "Imported comics from `{}` successfully",
+        source_dir.display()
+    );
+    Ok(import_report)
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn refresh_comics_metadata(app: AppHandle, ids: Option<Vec<i32>>) -> RefreshReport {
+    let refresh_report = library::refresh_comics_metadata(&app, ids).await;
+    tracing::debug!("refresh comics metadata success");
+    refresh_report
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+#[allow(clippy::needless_pass_by_value)]
+pub fn write_sidecars(
+    config: State<RwLock<Config>>,
+    ids: Option<Vec<i32>>,
+    formats: Vec<SidecarFormat>,
+) -> CommandResult<()> {
+    let download_dir = config.read().download_dir.clone();
+    let comics = library::get_downloaded_comics(&download_dir)
+        .into_iter()
+        .filter(|comic| ids.as_ref().is_none_or(|ids| ids.contains(&comic.id)));
+
+    for comic in comics {
+        let Some(comic_download_dir) = comic.comic_download_dir.as_ref() else {
+            continue;
+        };
+        // The sidecars of comics stored as CBZ are packed into the archive when it's created
+        if comic.is_stored_as_cbz() {
+            continue;
         }
-        // Choose the first one as the retained comic
-        let chosen_comic = comics.remove(0);
-        unique_comics.push(chosen_comic);
+        sidecar::write_sidecars(&comic, comic_download_dir, &formats).map_err(|err| {
+            let err_title = format!("Failed to write sidecar metadata of `{}`", comic.title);
+            CommandError::from(&err_title, err)
+        })?;
     }
-
-    unique_comics
+    tracing::debug!("write sidecars success");
+    Ok(())
 }
 
 #[tauri::command(async)]
@@ -313,7 +403,8 @@ pub fn get_downloaded_comics(config: State<RwLock<Config>>) -> Vec<Comic> {
 #[allow(clippy::needless_pass_by_value)]
 pub fn export_pdf(app: AppHandle, comic: Comic) -> CommandResult<()> {
     let title = &comic.title;
-    export::pdf(&app, &comic).map_err(|err| {
+    let event_uuid = uuid::Uuid::new_v4().to_string();
+    export::pdf(&app, &comic, &event_uuid).map_err(|err| {
         CommandError::from(&format!("Failed to export pdf for comic `{title}`"), err)
     })?;
     tracing::debug!("Exported pdf for comic `{title}` successfully");
@@ -325,13 +416,82 @@ pub fn export_pdf(app: AppHandle, comic: Comic) -> CommandResult<()> {
 #[allow(clippy::needless_pass_by_value)]
 pub fn export_cbz(app: AppHandle, comic: Comic) -> CommandResult<()> {
     let title = &comic.title;
-    export::cbz(&app, &comic).map_err(|err| {
+    let event_uuid = uuid::Uuid::new_v4().to_string();
+    export::cbz(&app, &comic, &event_uuid).map_err(|err| {
         CommandError::from(&format!("Failed to export cbz for comic `{title}`"), err)
     })?;
     tracing::debug!("Exported cbz for comic `{title}` successfully");
     Ok(())
 }
 
+#[tauri::command(async)]
+#[specta::specta]
+#[allow(clippy::needless_pass_by_value)]
+pub fn export_epub(app: AppHandle, comic: Comic) -> CommandResult<()> {
+    let title = &comic.title;
+    let event_uuid = uuid::Uuid::new_v4().to_string();
+    export::epub(&app, &comic, &event_uuid).map_err(|err| {
+        CommandError::from(&format!("Failed to export epub for comic `{title}`"), err)
+    })?;
+    tracing::debug!("Exported epub for comic `{title}` successfully");
+    Ok(())
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn export_comics(
+    app: AppHandle,
+    comics: Vec<Comic>,
+    formats: Vec<ExportFormat>,
+) -> ExportBatchReport {
+    let export_batch_report = export::export_comics(&app, comics, &formats).await;
+    tracing::debug!("export comics success");
+    export_batch_report
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+#[allow(clippy::needless_pass_by_value)]
+pub fn get_export_status_report(
+    app: AppHandle,
+    formats: Vec<ExportFormat>,
+) -> CommandResult<ExportStatusReport> {
+    let export_status_report = export::get_export_status_report(&app, &formats)
+        .map_err(|err| CommandError::from("Failed to get export status report", err))?;
+    tracing::debug!("get export status report success");
+    Ok(export_status_report)
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+#[allow(clippy::needless_pass_by_value)]
+pub fn cancel_export(
+    export_cancellations: State<ExportCancellations>,
+    uuid: String,
+) -> CommandResult<()> {
+    export_cancellations
+        .cancel(&uuid)
+        .map_err(|err| CommandError::from("Failed to cancel export", err))?;
+    tracing::debug!("Cancelled export `{uuid}` successfully");
+    Ok(())
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub fn get_device_profile_presets() -> Vec<DeviceProfile> {
+    DeviceProfile::presets()
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+#[allow(clippy::needless_pass_by_value)]
+pub fn sync_exports(app: AppHandle) -> Vec<SyncReport> {
+    let sync_reports = export::sync_exports(&app);
+    tracing::debug!("sync exports success");
+    sync_reports
+}
+
 #[tauri::command(async)]
 #[specta::specta]
 #[allow(clippy::needless_pass_by_value)]
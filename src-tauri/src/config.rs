@@ -4,7 +4,11 @@ use serde::{Deserialize, Serialize};
 use specta::Type;
 use tauri::{AppHandle, Manager};
 
-use crate::types::{DownloadFormat, ProxyMode};
+use crate::types::{
+    CbzCompression, DeviceProfile, DownloadFormat, ExistingExportPolicy, ExportFormat,
+    ExportSplitMode, PdfImageEncoding, ProxyMode, ReadingDirection, SidecarFormat, SpreadHandling,
+    StorageMode, SyncTarget,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
 #[serde(rename_all = "camelCase")]
@@ -17,6 +21,31 @@ pub struct Config {
     pub proxy_host: String,
     pub proxy_mode: ProxyMode,
     pub proxy_port: u16,
+    pub sidecar_formats: Vec<SidecarFormat>,
+    pub storage_mode: StorageMode,
+    pub reading_direction: ReadingDirection,
+    pub pdf_image_encoding: PdfImageEncoding,
+    /// 1-100, only used when `pdf_image_encoding` is `PdfImageEncoding::Jpeg`
+    pub pdf_jpeg_quality: u8,
+    pub cbz_compression: CbzCompression,
+    /// Only CBZ and PDF exports are split, an EPUB is always one file
+    pub export_split_mode: ExportSplitMode,
+    /// Only used when `export_split_mode` is `ExportSplitMode::PageCount`
+    pub export_pages_per_volume: u32,
+    /// Pages are processed for this device during CBZ, PDF and EPUB export, `None` exports them as they are
+    pub device_profile: Option<DeviceProfile>,
+    /// How CBZ, PDF and EPUB exports handle pages that are wider than they are tall
+    pub spread_handling: SpreadHandling,
+    /// Directories `sync_exports` mirrors `export_dir` to
+    pub sync_targets: Vec<SyncTarget>,
+    /// The number of comics `export_comics` exports at the same time
+    pub export_concurrency: u32,
+    pub existing_export_policy: ExistingExportPolicy,
+    /// Formats every comic is exported to once its download completes, empty to not export automatically
+    pub auto_export_formats: Vec<ExportFormat>,
+    /// Delete the downloaded images once all automatic exports succeed, `metadata.json` is kept.
+    /// Ignored with `StorageMode::Cbz`, where the images are in the archive
+    pub delete_images_after_auto_export: bool,
 }
 
 impl Config {
@@ -81,6 +110,21 @@ impl Config {
             proxy_mode: ProxyMode::System,
             proxy_host: "127.0.0.1".to_string(),
             proxy_port: 7890,
+            sidecar_formats: Vec::new(),
+            storage_mode: StorageMode::Folder,
+            reading_direction: ReadingDirection::LeftToRight,
+            pdf_image_encoding: PdfImageEncoding::Jpeg,
+            pdf_jpeg_quality: 90,
+            cbz_compression: CbzCompression::Deflate,
+            export_split_mode: ExportSplitMode::None,
+            export_pages_per_volume: 100,
+            device_profile: None,
+            spread_handling: SpreadHandling::Keep,
+            sync_targets: Vec::new(),
+            export_concurrency: 2,
+            existing_export_policy: ExistingExportPolicy::SkipUpToDate,
+            auto_export_formats: Vec::new(),
+            delete_images_after_auto_export: false,
         }
     }
 }
@@ -21,12 +21,15 @@ use tokio::{
 };
 
 use crate::{
+    cbz_storage,
     config::Config,
     events::{DownloadSpeedEvent, DownloadTaskEvent},
-    extensions::AnyhowErrorToStringChain,
+    export,
+    extensions::{AnyhowErrorToStringChain, PathIsImg},
     hitomi::{image_url_from_image, Ext},
     hitomi_client::HitomiClient,
-    types::{Comic, DownloadFormat},
+    library, sidecar,
+    types::{Comic, DownloadFormat, ExportFormat, StorageMode},
     utils::filename_filter,
 };
 
@@ -51,6 +54,8 @@ pub struct DownloadManager {
 pub enum DownloadTaskState {
     Pending,
     Downloading,
+    /// All images are downloaded and the comic is being exported to `Config::auto_export_formats`
+    Exporting,
     Paused,
     Cancelled,
     Completed,
@@ -73,13 +78,13 @@ impl DownloadManager {
     }
 
     pub fn create_download_task(&self, comic: Comic) -> anyhow::Result<()> {
-        use DownloadTaskState::{Downloading, Paused, Pending};
+        use DownloadTaskState::{Downloading, Exporting, Paused, Pending};
         let id = comic.id;
         let mut tasks = self.download_tasks.write();
         if let Some(task) = tasks.get(&id) {
-            // If the task already exists and the state is `Pending`, `Downloading`, or `Paused`, a new task will not be created
+            // If the task already exists and the state is `Pending`, `Downloading`, `Exporting`, or `Paused`, a new task will not be created
             let state = *task.state_sender.borrow();
-            if matches!(state, Pending | Downloading | Paused) {
+            if matches!(state, Pending | Downloading | Exporting | Paused) {
                 return Ok(());
             }
         }
@@ -95,12 +100,16 @@ impl DownloadManager {
         let Some(task) = tasks.get(&id) else {
             return Err(anyhow!("Can't find download task with id `{id}`"));
         };
+        // The images are already downloaded, an export can't be paused halfway
+        if *task.state_sender.borrow() == DownloadTaskState::Exporting {
+            return Err(anyhow!("Download task with id `{id}` is exporting"));
+        }
         task.set_state(DownloadTaskState::Paused);
         Ok(())
     }
 
     pub fn resume_download_task(&self, id: i32) -> anyhow::Result<()> {
-        use DownloadTaskState::{Cancelled, Completed, Failed, Pending};
+        use DownloadTaskState::{Cancelled, Completed, Exporting, Failed, Pending};
         let comic = {
             let tasks = self.download_tasks.read();
             let Some(task) = tasks.get(&id) else {
@@ -108,7 +117,10 @@ impl DownloadManager {
             };
             let task_state = *task.state_sender.borrow();
 
-            if matches!(task_state, Failed | Cancelled | Completed) {
+            if task_state == Exporting {
+                // An exporting task is never paused, there is nothing to resume
+                None
+            } else if matches!(task_state, Failed | Cancelled | Completed) {
                 // If the task state is `Failed`, `Cancelled`, or `Completed`, get the comic to recreate the download task
                 Some(task.comic.as_ref().clone())
             } else {
@@ -193,7 +205,11 @@ impl DownloadTask {
         state_receiver.mark_changed();
         let mut permit = None;
         loop {
-            let state_is_downloading = *state_receiver.borrow() == DownloadTaskState::Downloading;
+            // The task keeps running while it exports the downloaded comic
+            let state_is_downloading = matches!(
+                *state_receiver.borrow(),
+                DownloadTaskState::Downloading | DownloadTaskState::Exporting
+            );
             let state_is_pending = *state_receiver.borrow() == DownloadTaskState::Pending;
             tokio::select! {
                 () = &mut download_comic_task, if state_is_downloading && permit.is_some() => break,
@@ -281,33 +297,123 @@ impl DownloadTask {
             return;
         }
         // all images of this comic are downloaded successfully
-        let download_dir = match self.rename_temp_download_dir(&temp_download_dir) {
-            Ok(download_dir) => download_dir,
-            Err(err) => {
-                let err_title =
-                    format!("Failed to rename temp download directory of `{comic_title}`");
-                let string_chain = err.to_string_chain();
-                tracing::error!(err_title, message = string_chain);
+        let storage_mode = self.app.state::<RwLock<Config>>().read().storage_mode;
+        let stored_path = if storage_mode == StorageMode::Cbz {
+            // The metadata is packed into the archive along with the images
+            match self.pack_temp_download_dir(&temp_download_dir) {
+                Ok(cbz_path) => cbz_path,
+                Err(err) => {
+                    let err_title = format!(
+                        "Failed to pack temp download directory of `{comic_title}` into CBZ"
+                    );
+                    let string_chain = err.to_string_chain();
+                    tracing::error!(err_title, message = string_chain);
 
-                self.set_state(DownloadTaskState::Failed);
-                self.emit_download_task_update_event();
+                    self.set_state(DownloadTaskState::Failed);
+                    self.emit_download_task_update_event();
 
+                    return;
+                }
+            }
+        } else {
+            let download_dir = match self.rename_temp_download_dir(&temp_download_dir) {
+                Ok(download_dir) => download_dir,
+                Err(err) => {
+                    let err_title =
+                        format!("Failed to rename temp download directory of `{comic_title}`");
+                    let string_chain = err.to_string_chain();
+                    tracing::error!(err_title, message = string_chain);
+
+                    self.set_state(DownloadTaskState::Failed);
+                    self.emit_download_task_update_event();
+
+                    return;
+                }
+            };
+            // finally, save the metadata of this comic
+            if let Err(err) = self.save_metadata(&download_dir) {
+                let err_title = format!("Failed to save metadata of `{comic_title}`");
+                let string_chain = err.to_string_chain();
+                tracing::error!(err_title, message = string_chain);
                 return;
             }
+            download_dir
         };
-        // finally, save the metadata of this comic
-        if let Err(err) = self.save_metadata(&download_dir) {
-            let err_title = format!("Failed to save metadata of `{comic_title}`");
-            let string_chain = err.to_string_chain();
-            tracing::error!(err_title, message = string_chain);
-            return;
-        }
         tracing::info!(id, comic_title, "Comic download successfully");
 
+        self.auto_export(stored_path).await;
+
         self.set_state(DownloadTaskState::Completed);
         self.emit_download_task_update_event();
     }
 
+    /// Export the downloaded comic to `Config::auto_export_formats`, then delete the downloaded images
+    /// if `Config::delete_images_after_auto_export` is set and every export succeeded.
+    ///
+    /// The download already succeeded, so failed exports are only logged
+    async fn auto_export(&self, stored_path: PathBuf) {
+        let id = self.comic.id;
+        let comic_title = &self.comic.title;
+        let (auto_export_formats, delete_images, storage_mode) = {
+            let config = self.app.state::<RwLock<Config>>();
+            let config = config.read();
+            (
+                config.auto_export_formats.clone(),
+                config.delete_images_after_auto_export,
+                config.storage_mode,
+            )
+        };
+        if auto_export_formats.is_empty() {
+            return;
+        }
+
+        self.set_state(DownloadTaskState::Exporting);
+        self.emit_download_task_update_event();
+
+        let mut comic = self.comic.as_ref().clone();
+        comic.download_format = Some(self.download_format);
+        comic.is_downloaded = Some(true);
+        comic.comic_download_dir = Some(stored_path);
+
+        let mut all_exported = true;
+        for &format in &auto_export_formats {
+            let app = self.app.clone();
+            let comic = comic.clone();
+            let event_uuid = uuid::Uuid::new_v4().to_string();
+            let mut cancel_guard = AutoExportCancelGuard {
+                cancel_token: export::CancelToken::new(&self.app, &event_uuid),
+                finished: false,
+            };
+            // Exporting reads and encodes images synchronously, so it must not block the async runtime
+            let result = tauri::async_runtime::spawn_blocking(move || {
+                export::export(&app, &comic, format, &event_uuid)
+            })
+            .await
+            .map_err(|err| anyhow!("The export thread failed: {err}"))
+            .and_then(|result| result);
+            cancel_guard.finished = true;
+            if let Err(err) = result {
+                let extension = format.extension();
+                let err_title = format!("Failed to auto export {extension} for `{comic_title}`");
+                let string_chain = err.to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+                all_exported = false;
+            }
+        }
+        if all_exported {
+            tracing::debug!(id, comic_title, "Comic auto exported");
+        }
+
+        if !delete_images || !all_exported || storage_mode == StorageMode::Cbz {
+            return;
+        }
+        if let Err(err) = delete_downloaded_images(&self.app, &comic, &auto_export_formats) {
+            let err_title = format!("Failed to delete downloaded images of `{comic_title}`");
+            let string_chain = err.to_string_chain();
+            tracing::error!(err_title, message = string_chain);
+        }
+    }
+
     async fn get_img_urls(&self) -> Option<Vec<String>> {
         let id = self.comic.id;
         let comic_title = &self.comic.title;
@@ -513,26 +619,27 @@ impl DownloadTask {
         .emit(&self.app);
     }
 
-    #[allow(clippy::needless_pass_by_value)]
     pub fn save_metadata(&self, download_dir: &Path) -> anyhow::Result<()> {
         let mut comic = self.comic.as_ref().clone();
-        // Set the `is_downloaded` and `comic_download_dir` field to `None`
-        // so that the `is_downloaded` and `comic_download_dir` field is ignored during serialization
-        comic.is_downloaded = None;
-        comic.comic_download_dir = None;
-
-        let comic_title = &comic.title;
-        let comic_json = serde_json::to_string_pretty(&comic).context(format!(
-            "Failed to save metadata of `{comic_title}`, Failed to serialize Comic to json"
-        ))?;
-
-        let metadata_path = download_dir.join("metadata.json");
-
-        std::fs::write(&metadata_path, comic_json).context(format!(
-            "Failed to save metadata of `{comic_title}`, Failed to write json to `{}`",
-            metadata_path.display()
+        // Record the download format so that the images can be checked against it later
+        comic.download_format = Some(self.download_format);
+        comic.save_metadata(download_dir)?;
+        library::write_manifest(download_dir).context(format!(
+            "Failed to write checksum manifest of `{}`",
+            comic.title
         ))?;
-
+        // Sidecars are only a convenience for other tools, failing to write them doesn't fail the download
+        let sidecar_formats = self
+            .app
+            .state::<RwLock<Config>>()
+            .read()
+            .sidecar_formats
+            .clone();
+        if let Err(err) = sidecar::write_sidecars(&comic, download_dir, &sidecar_formats) {
+            let err_title = format!("Failed to write sidecar metadata of `{}`", comic.title);
+            let string_chain = err.to_string_chain();
+            tracing::error!(err_title, message = string_chain);
+        }
         Ok(())
     }
 
@@ -553,6 +660,12 @@ impl DownloadTask {
                 download_dir.display()
             ))?;
         }
+        // Replace the archive if the comic was downloaded with `StorageMode::Cbz` before
+        let cbz_path = cbz_storage::cbz_path_of(&download_dir);
+        if cbz_path.exists() {
+            std::fs::remove_file(&cbz_path)
+                .context(format!("Failed to delete `{}`", cbz_path.display()))?;
+        }
 
         std::fs::rename(temp_download_dir, &download_dir).context(format!(
             "Failed to rename `{}` to `{}`",
@@ -569,6 +682,113 @@ impl DownloadTask {
 
         Ok(download_dir)
     }
+
+    /// Pack the temporary download directory into the archive of `StorageMode::Cbz` and delete it,
+    /// return the path of the archive
+    fn pack_temp_download_dir(&self, temp_download_dir: &Path) -> anyhow::Result<PathBuf> {
+        let id = self.comic.id;
+        let comic_title = &self.comic.title;
+
+        let download_dir = self
+            .comic
+            .comic_download_dir
+            .clone()
+            .context("`comic_download_dir` is None")?;
+        let cbz_path = cbz_storage::cbz_path_of(&download_dir);
+
+        if let Some(parent) = cbz_path.parent() {
+            std::fs::create_dir_all(parent)
+                .context(format!("Failed to create directory `{}`", parent.display()))?;
+        }
+
+        let mut comic = self.comic.as_ref().clone();
+        // Record the download format so that the images can be checked against it later
+        comic.download_format = Some(self.download_format);
+        let sidecar_formats = self
+            .app
+            .state::<RwLock<Config>>()
+            .read()
+            .sidecar_formats
+            .clone();
+        cbz_storage::pack(&comic, temp_download_dir, &cbz_path, &sidecar_formats)?;
+        // Replace the folder if the comic was downloaded with another storage mode before
+        if download_dir.exists() {
+            std::fs::remove_dir_all(&download_dir).context(format!(
+                "Failed to delete directory `{}`",
+                download_dir.display()
+            ))?;
+        }
+        std::fs::remove_dir_all(temp_download_dir).context(format!(
+            "Failed to delete directory `{}`",
+            temp_download_dir.display()
+        ))?;
+
+        tracing::trace!(
+            id,
+            comic_title,
+            "Pack temp download directory into `{}` successfully",
+            cbz_path.display()
+        );
+
+        Ok(cbz_path)
+    }
+}
+
+/// Cancelling the download task drops the future that waits for an auto export, but not the export itself,
+/// which runs on a blocking thread. The guard cancels the export through its token in that case
+struct AutoExportCancelGuard {
+    cancel_token: export::CancelToken,
+    finished: bool,
+}
+
+impl Drop for AutoExportCancelGuard {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.cancel_token.cancel();
+        }
+    }
+}
+
+/// Delete the downloaded images of `comic`, other files such as `metadata.json` and `SHA256SUMS` are kept,
+/// the latter still verifies the exports. `Comic::images_deleted` is recorded in `metadata.json` first,
+/// so that the audit and the verification don't report the comic as broken
+///
+/// The source manifests of the exports in `formats` are rewritten afterwards, otherwise the exports would look stale.
+/// Exporting the comic again is refused from then on, so no export is replaced with an empty one
+fn delete_downloaded_images(
+    app: &AppHandle,
+    comic: &Comic,
+    formats: &[ExportFormat],
+) -> anyhow::Result<()> {
+    let comic_download_dir = comic
+        .comic_download_dir
+        .as_ref()
+        .context("`comic_download_dir` is None")?;
+    let mut comic = comic.clone();
+    comic.images_deleted = Some(true);
+    comic.save_metadata(comic_download_dir)?;
+
+    let image_paths: Vec<PathBuf> = std::fs::read_dir(comic_download_dir)
+        .context(format!(
+            "Failed to read directory `{}`",
+            comic_download_dir.display()
+        ))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_img())
+        .collect();
+    for image_path in image_paths {
+        std::fs::remove_file(&image_path)
+            .context(format!("Failed to delete `{}`", image_path.display()))?;
+    }
+
+    for &format in formats {
+        let source_manifest = export::SourceManifest::read(app, comic_download_dir, format)?;
+        for export_path in export::get_export_part_paths(app, &comic, format)? {
+            source_manifest.save(&export_path)?;
+        }
+    }
+    Ok(())
 }
 
 #[derive(Clone)]
@@ -661,7 +881,20 @@ impl DownloadImgTask {
 
         tracing::trace!(id, comic_title, url, "Image downloaded to memory");
         // save image
-        if let Err(err) = std::fs::write(&save_path, &img_data).map_err(anyhow::Error::from) {
+        let (download_dir, storage_mode) = {
+            let config = self.app.state::<RwLock<Config>>();
+            let config = config.read();
+            (config.download_dir.clone(), config.storage_mode)
+        };
+        let save_result = match storage_mode {
+            StorageMode::Folder | StorageMode::Cbz => {
+                std::fs::write(&save_path, &img_data).map_err(anyhow::Error::from)
+            }
+            StorageMode::DeduplicatedFolder => {
+                library::store_page(&download_dir, &img_data, &save_path)
+            }
+        };
+        if let Err(err) = save_result {
             let err_title = format!("Failed to save image `{}`", save_path.display());
             let string_chain = err.to_string_chain();
             tracing::error!(err_title, message = string_chain);
@@ -765,7 +998,7 @@ pub struct DirFmtParams {
 
 impl Comic {
     /// Update the `comic_download_dir` fields based on the fmt
-    fn update_dir_name_fields_by_fmt(&mut self, app: &AppHandle) -> anyhow::Result<()> {
+    pub fn update_dir_name_fields_by_fmt(&mut self, app: &AppHandle) -> anyhow::Result<()> {
         let comic_title = &self.title;
 
         let fmt_params = DirFmtParams {
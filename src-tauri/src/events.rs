@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 use specta::Type;
@@ -53,8 +53,16 @@ pub enum ExportPdfEvent {
     #[serde(rename_all = "camelCase")]
     Start { uuid: String, title: String },
     #[serde(rename_all = "camelCase")]
+    Progress {
+        uuid: String,
+        current: u32,
+        total: u32,
+    },
+    #[serde(rename_all = "camelCase")]
     Error { uuid: String },
     #[serde(rename_all = "camelCase")]
+    Cancelled { uuid: String },
+    #[serde(rename_all = "camelCase")]
     End { uuid: String },
 }
 
@@ -64,7 +72,95 @@ pub enum ExportCbzEvent {
     #[serde(rename_all = "camelCase")]
     Start { uuid: String, title: String },
     #[serde(rename_all = "camelCase")]
+    Progress {
+        uuid: String,
+        current: u32,
+        total: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    Error { uuid: String },
+    #[serde(rename_all = "camelCase")]
+    Cancelled { uuid: String },
+    #[serde(rename_all = "camelCase")]
+    End { uuid: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type, Event)]
+#[serde(tag = "event", content = "data")]
+pub enum ExportEpubEvent {
+    #[serde(rename_all = "camelCase")]
+    Start { uuid: String, title: String },
+    #[serde(rename_all = "camelCase")]
+    Progress {
+        uuid: String,
+        current: u32,
+        total: u32,
+    },
+    #[serde(rename_all = "camelCase")]
     Error { uuid: String },
     #[serde(rename_all = "camelCase")]
+    Cancelled { uuid: String },
+    #[serde(rename_all = "camelCase")]
     End { uuid: String },
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type, Event)]
+#[serde(tag = "event", content = "data")]
+pub enum RefreshMetadataEvent {
+    #[serde(rename_all = "camelCase")]
+    Start { uuid: String, total: u32 },
+    #[serde(rename_all = "camelCase")]
+    Progress {
+        uuid: String,
+        current: u32,
+        total: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    End { uuid: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type, Event)]
+#[serde(tag = "event", content = "data")]
+pub enum ExportBatchEvent {
+    #[serde(rename_all = "camelCase")]
+    Start { uuid: String, total: u32 },
+    #[serde(rename_all = "camelCase")]
+    Progress {
+        uuid: String,
+        current: u32,
+        total: u32,
+        failed: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    End { uuid: String },
+}
+
+/// Emitted once per target by `sync_exports`, `total` is the number of files in `export_dir`
+#[derive(Debug, Clone, Serialize, Deserialize, Type, Event)]
+#[serde(tag = "event", content = "data")]
+pub enum SyncExportsEvent {
+    #[serde(rename_all = "camelCase")]
+    Start {
+        uuid: String,
+        target: PathBuf,
+        total: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    Progress {
+        uuid: String,
+        current: u32,
+        total: u32,
+        failed: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    End { uuid: String },
+}
+
+/// Comics were added to, removed from or had their metadata edited in the download directory outside of the app
+#[derive(Debug, Clone, Serialize, Deserialize, Type, Event)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryChangedEvent {
+    pub added_ids: Vec<i32>,
+    pub removed_ids: Vec<i32>,
+    pub updated_ids: Vec<i32>,
+}
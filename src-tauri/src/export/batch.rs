@@ -0,0 +1,169 @@
+use anyhow::anyhow;
+use futures::StreamExt;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+
+use crate::{
+    config::Config,
+    events::ExportBatchEvent,
+    extensions::AnyhowErrorToStringChain,
+    types::{Comic, ExistingExportPolicy, ExportFormat},
+};
+
+use super::{export, get_export_part_paths, get_export_status, ExportStatus};
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportBatchReport {
+    pub exported: Vec<ExportJob>,
+    /// Jobs whose output already existed, or was up to date with `ExistingExportPolicy::SkipUpToDate`
+    pub skipped: Vec<ExportJob>,
+    pub failed: Vec<FailedExport>,
+}
+
+/// One comic exported to one format
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportJob {
+    pub id: i32,
+    pub title: String,
+    pub format: ExportFormat,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedExport {
+    pub id: i32,
+    pub title: String,
+    pub format: ExportFormat,
+    pub reason: String,
+}
+
+enum ExportJobResult {
+    Exported(ExportJob),
+    Skipped(ExportJob),
+    Failed(FailedExport),
+}
+
+/// Export every comic in `comics` to every format in `formats`
+///
+/// At most `Config::export_concurrency` exports run at the same time, each on a blocking thread.
+/// Existing outputs are skipped or overwritten according to `Config::existing_export_policy`
+pub async fn export_comics(
+    app: &AppHandle,
+    comics: Vec<Comic>,
+    formats: &[ExportFormat],
+) -> ExportBatchReport {
+    let (export_concurrency, existing_export_policy) = {
+        let config = app.state::<RwLock<Config>>();
+        let config = config.read();
+        (config.export_concurrency, config.existing_export_policy)
+    };
+
+    let jobs: Vec<(Comic, ExportFormat)> = comics
+        .into_iter()
+        .flat_map(|comic| formats.iter().map(move |format| (comic.clone(), *format)))
+        .collect();
+
+    let uuid = uuid::Uuid::new_v4().to_string();
+    #[allow(clippy::cast_possible_truncation)]
+    let total = jobs.len() as u32;
+    let _ = ExportBatchEvent::Start {
+        uuid: uuid.clone(),
+        total,
+    }
+    .emit(app);
+
+    let mut results = futures::stream::iter(jobs)
+        .map(|(comic, format)| run_export_job(app, comic, format, existing_export_policy))
+        .buffer_unordered(export_concurrency.max(1) as usize);
+
+    let mut report = ExportBatchReport::default();
+    let mut current = 0;
+    let mut failed = 0;
+    while let Some(result) = results.next().await {
+        match result {
+            ExportJobResult::Exported(job) => report.exported.push(job),
+            ExportJobResult::Skipped(job) => report.skipped.push(job),
+            ExportJobResult::Failed(failed_export) => {
+                report.failed.push(failed_export);
+                failed += 1;
+            }
+        }
+
+        current += 1;
+        let _ = ExportBatchEvent::Progress {
+            uuid: uuid.clone(),
+            current,
+            total,
+            failed,
+        }
+        .emit(app);
+    }
+
+    let _ = ExportBatchEvent::End { uuid }.emit(app);
+
+    report
+}
+
+async fn run_export_job(
+    app: &AppHandle,
+    comic: Comic,
+    format: ExportFormat,
+    existing_export_policy: ExistingExportPolicy,
+) -> ExportJobResult {
+    let job = ExportJob {
+        id: comic.id,
+        title: comic.title.clone(),
+        format,
+    };
+
+    match try_run_export_job(app, comic, format, existing_export_policy).await {
+        Ok(true) => ExportJobResult::Exported(job),
+        Ok(false) => ExportJobResult::Skipped(job),
+        Err(err) => {
+            let extension = format.extension();
+            let err_title = format!("Failed to export {extension} for comic `{}`", job.title);
+            let string_chain = err.to_string_chain();
+            tracing::error!(err_title, message = string_chain);
+            ExportJobResult::Failed(FailedExport {
+                id: job.id,
+                title: job.title,
+                format,
+                reason: string_chain,
+            })
+        }
+    }
+}
+
+/// Return `false` if the export was skipped because of `existing_export_policy`
+async fn try_run_export_job(
+    app: &AppHandle,
+    comic: Comic,
+    format: ExportFormat,
+    existing_export_policy: ExistingExportPolicy,
+) -> anyhow::Result<bool> {
+    let export_paths = get_export_part_paths(app, &comic, format)?;
+    let skip = match existing_export_policy {
+        ExistingExportPolicy::Skip => export_paths.iter().all(|path| path.exists()),
+        ExistingExportPolicy::SkipUpToDate => {
+            get_export_status(app, &comic, format, &export_paths)? == ExportStatus::Current
+        }
+        ExistingExportPolicy::Overwrite => false,
+    };
+    if skip {
+        return Ok(false);
+    }
+
+    // Exporting reads and encodes images synchronously, so it must not block the async runtime
+    let app = app.clone();
+    let event_uuid = uuid::Uuid::new_v4().to_string();
+    tauri::async_runtime::spawn_blocking(move || export(&app, &comic, format, &event_uuid))
+        .await
+        .map_err(|err| anyhow!("The export thread failed: {err}"))??;
+
+    Ok(true)
+}
@@ -0,0 +1,81 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::{anyhow, Context};
+use parking_lot::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// Cancellation flags of the running exports, keyed by the uuid of their events
+#[derive(Default)]
+pub struct ExportCancellations {
+    flags: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl ExportCancellations {
+    /// Ask the export whose events have `uuid` to stop, it stops before its next page and deletes its unfinished output
+    pub fn cancel(&self, uuid: &str) -> anyhow::Result<()> {
+        let flags = self.flags.lock();
+        let flag = flags
+            .get(uuid)
+            .context(format!("Export `{uuid}` is not running"))?;
+        flag.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Registered in `ExportCancellations` for as long as an export runs
+pub struct CancelToken {
+    app: AppHandle,
+    uuid: String,
+    flag: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Tokens created with the same `uuid` share their flag, so a caller can hold a token for an export
+    /// it starts on another thread and cancel it with `cancel`
+    pub fn new(app: &AppHandle, uuid: &str) -> CancelToken {
+        let flag = app
+            .state::<ExportCancellations>()
+            .flags
+            .lock()
+            .entry(uuid.to_string())
+            .or_insert_with(|| Arc::new(AtomicBool::new(false)))
+            .clone();
+        CancelToken {
+            app: app.clone(),
+            uuid: uuid.to_string(),
+            flag,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    /// Return an error if the export was cancelled, exports call this before every page
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.is_cancelled() {
+            return Err(anyhow!("Export `{}` was cancelled", self.uuid));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CancelToken {
+    fn drop(&mut self) {
+        self.app
+            .state::<ExportCancellations>()
+            .flags
+            .lock()
+            .remove(&self.uuid);
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::Context;
+use image::{
+    codecs::{jpeg::JpegEncoder, png::PngEncoder},
+    imageops::FilterType,
+    DynamicImage, GenericImageView, ImageEncoder,
+};
+
+use crate::types::{DeviceImageFormat, DeviceProfile};
+
+/// A pixel belongs to the margin if its luma differs from the margin color by at most this much
+const MARGIN_TOLERANCE: u8 = 24;
+/// Cropping never removes more than this fraction of the width or the height, so pages with little content keep their layout
+const MAX_MARGIN_CROP: f32 = 0.15;
+
+/// A page after going through the pipeline of a `DeviceProfile`
+pub struct ProcessedPage {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Run `img` through the pipeline of `profile`: crop margins, scale down to the screen,
+/// convert to grayscale, adjust gamma and contrast, then encode in `DeviceProfile::image_format`
+pub fn process_image(
+    mut img: DynamicImage,
+    profile: &DeviceProfile,
+) -> anyhow::Result<ProcessedPage> {
+    if profile.crop_margins {
+        img = crop_margins(img);
+    }
+    if img.width() > profile.width || img.height() > profile.height {
+        img = img.resize(profile.width, profile.height, FilterType::Lanczos3);
+    }
+    img = if profile.grayscale {
+        DynamicImage::ImageLuma8(img.to_luma8())
+    } else {
+        DynamicImage::ImageRgb8(img.to_rgb8())
+    };
+    #[allow(clippy::float_cmp)]
+    let (adjusts_gamma, adjusts_contrast) = (
+        profile.gamma > 0.0 && profile.gamma != 1.0,
+        profile.contrast != 0.0,
+    );
+    if adjusts_gamma {
+        apply_gamma(&mut img, profile.gamma);
+    }
+    if adjusts_contrast {
+        img = img.adjust_contrast(profile.contrast);
+    }
+
+    let (width, height) = img.dimensions();
+    let mut data = Vec::new();
+    match profile.image_format {
+        DeviceImageFormat::Jpeg => {
+            JpegEncoder::new_with_quality(&mut data, profile.jpeg_quality.clamp(1, 100))
+                .write_image(img.as_bytes(), width, height, img.color().into())
+                .context("Failed to encode image as JPEG")?;
+        }
+        DeviceImageFormat::Png => {
+            PngEncoder::new(&mut data)
+                .write_image(img.as_bytes(), width, height, img.color().into())
+                .context("Failed to encode image as PNG")?;
+        }
+    }
+
+    Ok(ProcessedPage {
+        data,
+        width,
+        height,
+    })
+}
+
+/// The margin color is taken from the top-left corner, usually white paper or a black background
+fn crop_margins(img: DynamicImage) -> DynamicImage {
+    let luma = img.to_luma8();
+    let (width, height) = luma.dimensions();
+    if width == 0 || height == 0 {
+        return img;
+    }
+    let margin_luma = luma.get_pixel(0, 0).0[0];
+    let is_content =
+        |x: u32, y: u32| luma.get_pixel(x, y).0[0].abs_diff(margin_luma) > MARGIN_TOLERANCE;
+    let row_has_content = |y: u32| (0..width).any(|x| is_content(x, y));
+    let column_has_content = |x: u32| (0..height).any(|y| is_content(x, y));
+
+    let Some(top) = (0..height).find(|y| row_has_content(*y)) else {
+        // A blank page has nothing to crop to
+        return img;
+    };
+    let bottom = (0..height)
+        .rev()
+        .find(|y| row_has_content(*y))
+        .unwrap_or(height - 1);
+    let left = (0..width).find(|x| column_has_content(*x)).unwrap_or(0);
+    let right = (0..width)
+        .rev()
+        .find(|x| column_has_content(*x))
+        .unwrap_or(width - 1);
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    #[allow(clippy::cast_precision_loss)]
+    let (max_crop_x, max_crop_y) = (
+        (width as f32 * MAX_MARGIN_CROP) as u32,
+        (height as f32 * MAX_MARGIN_CROP) as u32,
+    );
+    let left = left.min(max_crop_x);
+    let top = top.min(max_crop_y);
+    let right = right.max(width - 1 - max_crop_x);
+    let bottom = bottom.max(height - 1 - max_crop_y);
+
+    img.crop_imm(left, top, right - left + 1, bottom - top + 1)
+}
+
+/// `img` must be `Luma8` or `Rgb8`
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+fn apply_gamma(img: &mut DynamicImage, gamma: f32) {
+    let lookup_table: Vec<u8> = (0..=255u8)
+        .map(|value| {
+            let normalized = f32::from(value) / 255.0;
+            (normalized.powf(gamma) * 255.0).round().clamp(0.0, 255.0) as u8
+        })
+        .collect();
+    let pixels: &mut [u8] = match img {
+        DynamicImage::ImageLuma8(luma) => luma,
+        DynamicImage::ImageRgb8(rgb) => rgb,
+        _ => return,
+    };
+    for pixel in pixels {
+        *pixel = lookup_table[usize::from(*pixel)];
+    }
+}
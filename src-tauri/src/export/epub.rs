@@ -0,0 +1,475 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use image::ImageFormat;
+use parking_lot::RwLock;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    config::Config,
+    events::ExportEpubEvent,
+    image_codec,
+    library::ComicStorage,
+    types::{Comic, ExportFormat, ReadingDirection},
+};
+
+use super::{
+    escape_xml, finish_output, get_export_page_names,
+    page_pool::for_each_prepared_page,
+    partial_path_of, remove_unfinished_output,
+    spread::{encode_png, export_images, ExportImage, SpreadLayout},
+    CancelToken, SourceManifest,
+};
+
+/// A page of the EPUB, which is an XHTML document that shows one image
+struct EpubPage {
+    /// Name of the image in `OEBPS/images`, the same as in the comic download directory
+    /// unless a device profile, the spread handling or the re-encoding of a non-core format changed it
+    image_name: String,
+    media_type: &'static str,
+    width: u32,
+    height: u32,
+}
+
+impl EpubPage {
+    /// `0001` for the first page, used in both item ids and file names
+    fn stem(index: usize) -> String {
+        format!("{:04}", index + 1)
+    }
+}
+
+struct EpubEventGuard {
+    uuid: String,
+    app: AppHandle,
+    success: bool,
+    cancel_token: CancelToken,
+    /// Written to `<path>.part` first, which is deleted if the export doesn't succeed so an earlier export is kept
+    output_paths: Vec<PathBuf>,
+}
+
+impl Drop for EpubEventGuard {
+    fn drop(&mut self) {
+        let uuid = self.uuid.clone();
+
+        if !self.success {
+            remove_unfinished_output(&self.output_paths);
+        }
+        let _ = if self.success {
+            ExportEpubEvent::End { uuid }.emit(&self.app)
+        } else if self.cancel_token.is_cancelled() {
+            ExportEpubEvent::Cancelled { uuid }.emit(&self.app)
+        } else {
+            ExportEpubEvent::Error { uuid }.emit(&self.app)
+        };
+    }
+}
+
+/// Export `comic` as a fixed-layout EPUB 3, one page per image, so that it can be read on e-readers
+#[allow(clippy::cast_possible_truncation)]
+pub fn epub(app: &AppHandle, comic: &Comic, event_uuid: &str) -> anyhow::Result<()> {
+    let comic_title = &comic.title;
+    let event_uuid = event_uuid.to_string();
+
+    let _ = ExportEpubEvent::Start {
+        uuid: event_uuid.clone(),
+        title: comic_title.clone(),
+    }
+    .emit(app);
+    // Event guard to ensure that the error event is sent if the function panics
+    let mut epub_event_guard = EpubEventGuard {
+        uuid: event_uuid.clone(),
+        app: app.clone(),
+        success: false,
+        cancel_token: CancelToken::new(app, &event_uuid),
+        output_paths: Vec::new(),
+    };
+
+    let download_dir = comic
+        .comic_download_dir
+        .as_ref()
+        .context("`comic_download_dir` field is `None`")?;
+    // The comic may be stored as a folder or as a CBZ archive
+    let mut comic_storage = ComicStorage::open(download_dir)?;
+    let page_names = get_export_page_names(&comic_storage, comic)?;
+    // Read before exporting, so that pages changed during the export make it stale
+    let source_manifest = SourceManifest::read(app, download_dir, ExportFormat::Epub)?;
+    let total = page_names.len() as u32;
+
+    let reading_direction = app.state::<RwLock<Config>>().read().reading_direction;
+    let export_dir = comic
+        .get_comic_export_dir(app)
+        .context("Failed to get comic export directory")?;
+    // Ensure export directory exists
+    std::fs::create_dir_all(&export_dir).context(format!(
+        "`{comic_title}` failed to create directory `{}`",
+        export_dir.display()
+    ))?;
+    // Create epub file
+    let extension = ExportFormat::Epub.extension();
+    let download_dir_name = &comic
+        .get_comic_download_dir_name()
+        .context("Failed to get comic download directory name")?;
+    let epub_path = export_dir.join(format!("{download_dir_name}.{extension}"));
+    // It replaces the earlier export only once it's finished
+    epub_event_guard.output_paths.push(epub_path.clone());
+    let partial_path = partial_path_of(&epub_path);
+    let epub_file = File::create(&partial_path).context(format!(
+        "`{comic_title}` failed to create file `{}`",
+        partial_path.display()
+    ))?;
+    let mut zip_writer = ZipWriter::new(epub_file);
+    // `mimetype` must be the first entry and must not be compressed, readers identify EPUB files by it
+    let stored_options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    write_entry(
+        &mut zip_writer,
+        "mimetype",
+        b"application/epub+zip",
+        stored_options,
+    )?;
+    write_entry(
+        &mut zip_writer,
+        "META-INF/container.xml",
+        CONTAINER_XML.as_bytes(),
+        SimpleFileOptions::default(),
+    )?;
+
+    // Spreads are handled and pages are processed for the device profile if there is one,
+    // a page that needs neither is stored as it is
+    let (device_profile, spread_handling) = {
+        let config = app.state::<RwLock<Config>>();
+        let config = config.read();
+        (config.device_profile.clone(), config.spread_handling)
+    };
+    let spread_layout = SpreadLayout::new(comic, spread_handling, reading_direction);
+    // Bookmarks have to point to the pages of the EPUB, which differ from the pages of the comic if spreads are split
+    let spread_comic = spread_layout.comic(comic);
+    let prepare = |index: usize, page_path: &str, buffer: Vec<u8>| {
+        let transforms = spread_layout.page_transforms(index);
+        export_images(
+            &page_names[index],
+            buffer,
+            transforms,
+            device_profile.as_ref(),
+        )
+        .and_then(|export_images| {
+            export_images
+                .into_iter()
+                .map(to_core_media_type)
+                .collect::<anyhow::Result<Vec<ExportImage>>>()
+        })
+        .context(format!("Failed to prepare `{page_path}` for export"))
+    };
+    let mut pages = Vec::with_capacity(spread_comic.files.len());
+    let mut current = 0;
+    let write = |_: &str, export_images: Vec<ExportImage>| -> anyhow::Result<()> {
+        for export_image in export_images {
+            let page = EpubPage {
+                media_type: image_media_type(&export_image.name),
+                image_name: export_image.name,
+                width: export_image.width,
+                height: export_image.height,
+            };
+            // The images are already compressed, deflating them again only costs time
+            write_entry(
+                &mut zip_writer,
+                &format!("OEBPS/images/{}", page.image_name),
+                &export_image.data,
+                stored_options,
+            )?;
+            write_entry(
+                &mut zip_writer,
+                &format!("OEBPS/pages/{}.xhtml", EpubPage::stem(pages.len())),
+                render_page_xhtml(&spread_comic, &page).as_bytes(),
+                SimpleFileOptions::default(),
+            )?;
+            pages.push(page);
+        }
+
+        current += 1;
+        let _ = ExportEpubEvent::Progress {
+            uuid: event_uuid.clone(),
+            current,
+            total,
+        }
+        .emit(app);
+        Ok(())
+    };
+    for_each_prepared_page(
+        &mut comic_storage,
+        &page_names,
+        &epub_event_guard.cancel_token,
+        prepare,
+        write,
+    )?;
+
+    write_entry(
+        &mut zip_writer,
+        "OEBPS/nav.xhtml",
+        render_nav_xhtml(&spread_comic, &pages).as_bytes(),
+        SimpleFileOptions::default(),
+    )?;
+    write_entry(
+        &mut zip_writer,
+        "OEBPS/content.opf",
+        render_content_opf(&spread_comic, &pages, reading_direction).as_bytes(),
+        SimpleFileOptions::default(),
+    )?;
+    zip_writer.finish().context(format!(
+        "`{comic_title}` failed to close `{}`",
+        epub_path.display()
+    ))?;
+    finish_output(&epub_event_guard.output_paths, &source_manifest)?;
+    // Set success to true to ensure that the end event is sent
+    epub_event_guard.success = true;
+
+    Ok(())
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+fn write_entry(
+    zip_writer: &mut ZipWriter<File>,
+    name: &str,
+    data: &[u8],
+    options: SimpleFileOptions,
+) -> anyhow::Result<()> {
+    zip_writer
+        .start_file(name, options)
+        .context(format!("Failed to create `{name}` in epub"))?;
+    zip_writer
+        .write_all(data)
+        .context(format!("Failed to write `{name}` to epub"))?;
+    Ok(())
+}
+
+/// Re-encode `export_image` as PNG unless it's in one of the image formats every EPUB reader must support,
+/// so that AVIF and JPEG XL pages still show up
+fn to_core_media_type(export_image: ExportImage) -> anyhow::Result<ExportImage> {
+    let format = image::guess_format(&export_image.data).ok();
+    if matches!(
+        format,
+        Some(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Gif | ImageFormat::WebP)
+    ) {
+        return Ok(export_image);
+    }
+
+    let img = image_codec::decode(&export_image.data)?;
+    let data = encode_png(&img)?;
+    let name = Path::new(&export_image.name)
+        .with_extension("png")
+        .to_string_lossy()
+        .to_string();
+    Ok(ExportImage {
+        name,
+        data,
+        ..export_image
+    })
+}
+
+fn image_media_type(image_name: &str) -> &'static str {
+    let extension = Path::new(image_name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        _ => "image/webp",
+    }
+}
+
+/// The viewport has the size of the image, so the reader scales the whole page to fit the screen
+fn render_page_xhtml(comic: &Comic, page: &EpubPage) -> String {
+    let title = escape_xml(&comic.title);
+    let image_name = escape_xml(&page.image_name);
+    let EpubPage { width, height, .. } = page;
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+  <title>{title}</title>
+  <meta name="viewport" content="width={width}, height={height}"/>
+  <style>html, body {{ margin: 0; padding: 0; }} img {{ display: block; width: {width}px; height: {height}px; }}</style>
+</head>
+<body>
+  <img src="../images/{image_name}" alt=""/>
+</body>
+</html>
+"#
+    )
+}
+
+/// The table of contents has an entry for every scene like the bookmarks of the CBZ export,
+/// or a single entry with the title if the comic has no scenes
+fn render_nav_xhtml(comic: &Comic, pages: &[EpubPage]) -> String {
+    let title = escape_xml(&comic.title);
+    let first_page = EpubPage::stem(0);
+    let mut toc = String::new();
+    let scene_pages = comic
+        .scene_indexes
+        .iter()
+        .filter_map(|scene_index| usize::try_from(*scene_index).ok())
+        .filter(|scene_index| *scene_index < pages.len());
+    for (scene, scene_index) in scene_pages.enumerate() {
+        let _ = writeln!(
+            toc,
+            r#"      <li><a href="pages/{}.xhtml">Scene {}</a></li>"#,
+            EpubPage::stem(scene_index),
+            scene + 1
+        );
+    }
+    if toc.is_empty() {
+        let _ = writeln!(
+            toc,
+            r#"      <li><a href="pages/{first_page}.xhtml">{title}</a></li>"#
+        );
+    }
+    let mut landmarks = String::new();
+    if !pages.is_empty() {
+        let _ = writeln!(
+            landmarks,
+            r#"      <li><a epub:type="cover" href="pages/{first_page}.xhtml">Cover</a></li>"#
+        );
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+  <title>{title}</title>
+</head>
+<body>
+  <nav epub:type="toc" id="toc">
+    <ol>
+{toc}    </ol>
+  </nav>
+  <nav epub:type="landmarks" hidden="">
+    <ol>
+{landmarks}    </ol>
+  </nav>
+</body>
+</html>
+"#
+    )
+}
+
+/// The cover is the first page, marked as `cover-image` for EPUB 3 readers and with `<meta name="cover">` for Kindle
+fn render_content_opf(
+    comic: &Comic,
+    pages: &[EpubPage],
+    reading_direction: ReadingDirection,
+) -> String {
+    let mut metadata = String::new();
+    let _ = writeln!(
+        metadata,
+        r#"    <dc:identifier id="book-id">urn:hitomi:{}</dc:identifier>"#,
+        comic.id
+    );
+    let _ = writeln!(
+        metadata,
+        "    <dc:title>{}</dc:title>",
+        escape_xml(&comic.title)
+    );
+    // `und` is the BCP 47 code for an undetermined language, `dc:language` is required
+    let language = comic.language_iso().unwrap_or("und");
+    let _ = writeln!(metadata, "    <dc:language>{language}</dc:language>");
+    for artist in &comic.artists {
+        let _ = writeln!(
+            metadata,
+            "    <dc:creator>{}</dc:creator>",
+            escape_xml(artist)
+        );
+    }
+    for tag in &comic.tags {
+        let _ = writeln!(
+            metadata,
+            "    <dc:subject>{}</dc:subject>",
+            escape_xml(&tag.name_with_namespace())
+        );
+    }
+    let _ = writeln!(metadata, "    <dc:publisher>Hitomi</dc:publisher>");
+    let _ = writeln!(
+        metadata,
+        "    <dc:source>{}</dc:source>",
+        escape_xml(&comic.gallery_url())
+    );
+    let modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+    let _ = writeln!(
+        metadata,
+        r#"    <meta property="dcterms:modified">{modified}</meta>"#
+    );
+    let _ = writeln!(
+        metadata,
+        r#"    <meta property="rendition:layout">pre-paginated</meta>"#
+    );
+    let _ = writeln!(
+        metadata,
+        r#"    <meta property="rendition:orientation">auto</meta>"#
+    );
+    let _ = writeln!(
+        metadata,
+        r#"    <meta property="rendition:spread">none</meta>"#
+    );
+    if !pages.is_empty() {
+        let cover_id = format!("image-{}", EpubPage::stem(0));
+        let _ = writeln!(metadata, r#"    <meta name="cover" content="{cover_id}"/>"#);
+    }
+
+    let mut manifest = String::new();
+    let _ = writeln!(
+        manifest,
+        r#"    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#
+    );
+    let mut spine = String::new();
+    for (i, page) in pages.iter().enumerate() {
+        let stem = EpubPage::stem(i);
+        let image_name = escape_xml(&page.image_name);
+        let media_type = page.media_type;
+        let properties = if i == 0 {
+            r#" properties="cover-image""#
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            manifest,
+            r#"    <item id="image-{stem}" href="images/{image_name}" media-type="{media_type}"{properties}/>"#
+        );
+        let _ = writeln!(
+            manifest,
+            r#"    <item id="page-{stem}" href="pages/{stem}.xhtml" media-type="application/xhtml+xml"/>"#
+        );
+        let _ = writeln!(spine, r#"    <itemref idref="page-{stem}"/>"#);
+    }
+
+    let page_progression_direction = match reading_direction {
+        ReadingDirection::LeftToRight => "ltr",
+        ReadingDirection::RightToLeft => "rtl",
+    };
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" prefix="rendition: http://www.idpf.org/vocab/rendition/#">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+{metadata}  </metadata>
+  <manifest>
+{manifest}  </manifest>
+  <spine page-progression-direction="{page_progression_direction}">
+{spine}  </spine>
+</package>
+"#
+    )
+}
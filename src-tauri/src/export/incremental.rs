@@ -0,0 +1,338 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::Context;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{AppHandle, Manager};
+use walkdir::WalkDir;
+
+use crate::{
+    config::Config,
+    extensions::AnyhowErrorToStringChain,
+    library,
+    types::{
+        CbzCompression, Comic, DeviceProfile, ExportFormat, ExportSplitMode, PdfImageEncoding,
+        ReadingDirection, SpreadHandling,
+    },
+};
+
+use super::{get_export_part_paths, get_export_paths, PARTIAL_SUFFIX, SYNC_STATE_FILENAME};
+
+/// Appended to the name of an export to get the name of its source manifest
+pub const SOURCE_MANIFEST_SUFFIX: &str = ".source.json";
+
+/// Size and modification time of every file an export was made from, and the settings it was made with.
+/// It is saved next to the export, an export whose source files or settings no longer match it is stale
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceManifest {
+    /// Keyed by the file name in `comic_download_dir`
+    pub files: BTreeMap<String, SourceFile>,
+    /// Hash of the `ExportSettings` of the format, empty in manifests written before it was added
+    #[serde(default)]
+    pub settings_hash: String,
+}
+
+/// The settings of `Config` that change what exporting to a format produces
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportSettings {
+    reading_direction: ReadingDirection,
+    device_profile: Option<DeviceProfile>,
+    spread_handling: SpreadHandling,
+    /// `None` for EPUB, which is never split
+    export_split_mode: Option<ExportSplitMode>,
+    /// `None` unless the export is split by page count
+    export_pages_per_volume: Option<u32>,
+    /// `None` for the formats other than CBZ
+    cbz_compression: Option<CbzCompression>,
+    /// `None` for the formats other than PDF
+    pdf_image_encoding: Option<PdfImageEncoding>,
+    /// `None` unless PDF images are encoded as JPEG
+    pdf_jpeg_quality: Option<u8>,
+}
+
+impl ExportSettings {
+    fn new(config: &Config, format: ExportFormat) -> ExportSettings {
+        let export_split_mode = (format != ExportFormat::Epub).then_some(config.export_split_mode);
+        let is_pdf = format == ExportFormat::Pdf;
+        ExportSettings {
+            reading_direction: config.reading_direction,
+            device_profile: config.device_profile.clone(),
+            spread_handling: config.spread_handling,
+            export_split_mode,
+            export_pages_per_volume: (export_split_mode == Some(ExportSplitMode::PageCount))
+                .then_some(config.export_pages_per_volume),
+            cbz_compression: (format == ExportFormat::Cbz).then_some(config.cbz_compression),
+            pdf_image_encoding: is_pdf.then_some(config.pdf_image_encoding),
+            pdf_jpeg_quality: (is_pdf && config.pdf_image_encoding == PdfImageEncoding::Jpeg)
+                .then_some(config.pdf_jpeg_quality),
+        }
+    }
+
+    fn hash(&self) -> anyhow::Result<String> {
+        let settings_json =
+            serde_json::to_string(self).context("Failed to serialize export settings")?;
+        Ok(library::sha256_hex(settings_json.as_bytes()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceFile {
+    pub size: u64,
+    /// Milliseconds since the Unix epoch
+    pub modified: i64,
+}
+
+impl SourceFile {
+    pub fn read(path: &Path) -> anyhow::Result<SourceFile> {
+        let metadata = std::fs::metadata(path)
+            .context(format!("Failed to get metadata of `{}`", path.display()))?;
+        let modified = metadata
+            .modified()
+            .context(format!(
+                "Failed to get modification time of `{}`",
+                path.display()
+            ))?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        #[allow(clippy::cast_possible_truncation)]
+        let source_file = SourceFile {
+            size: metadata.len(),
+            modified: modified.as_millis() as i64,
+        };
+        Ok(source_file)
+    }
+}
+
+impl SourceManifest {
+    /// Read the files of `comic_download_dir`, which may be a folder or a CBZ archive,
+    /// along with the current settings for exporting to `format`
+    pub fn read(
+        app: &AppHandle,
+        comic_download_dir: &Path,
+        format: ExportFormat,
+    ) -> anyhow::Result<SourceManifest> {
+        let settings_hash = {
+            let config = app.state::<RwLock<Config>>();
+            let config = config.read();
+            ExportSettings::new(&config, format).hash()?
+        };
+
+        let paths = if comic_download_dir.is_file() {
+            vec![comic_download_dir.to_path_buf()]
+        } else {
+            std::fs::read_dir(comic_download_dir)
+                .context(format!(
+                    "Failed to read directory `{}`",
+                    comic_download_dir.display()
+                ))?
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect()
+        };
+
+        let mut files = BTreeMap::new();
+        for path in paths {
+            let Some(filename) = path.file_name() else {
+                continue;
+            };
+            files.insert(
+                filename.to_string_lossy().to_string(),
+                SourceFile::read(&path)?,
+            );
+        }
+
+        Ok(SourceManifest {
+            files,
+            settings_hash,
+        })
+    }
+
+    /// Save the manifest next to `export_path`
+    pub fn save(&self, export_path: &Path) -> anyhow::Result<()> {
+        let manifest_path = source_manifest_path(export_path);
+        let manifest_json =
+            serde_json::to_string_pretty(self).context("Failed to serialize source manifest")?;
+        std::fs::write(&manifest_path, manifest_json)
+            .context(format!("Failed to write `{}`", manifest_path.display()))?;
+        Ok(())
+    }
+
+    /// Load the manifest saved next to `export_path`, `None` if there is none or it can't be parsed
+    fn load(export_path: &Path) -> Option<SourceManifest> {
+        let manifest_path = source_manifest_path(export_path);
+        let manifest_json = std::fs::read_to_string(manifest_path).ok()?;
+        serde_json::from_str(&manifest_json).ok()
+    }
+}
+
+/// `<export_path>.source.json`
+pub fn source_manifest_path(export_path: &Path) -> PathBuf {
+    let mut manifest_path = OsString::from(export_path.as_os_str());
+    manifest_path.push(SOURCE_MANIFEST_SUFFIX);
+    PathBuf::from(manifest_path)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum ExportStatus {
+    Missing,
+    /// The export exists, but the comic changed since it was made, or it has no source manifest
+    Stale,
+    Current,
+}
+
+/// `export_paths` are the files of the export of `comic` to `format`, more than one if it is split into volumes.
+/// The export is missing if any of them is missing, and stale if any of them is stale
+pub fn get_export_status(
+    app: &AppHandle,
+    comic: &Comic,
+    format: ExportFormat,
+    export_paths: &[PathBuf],
+) -> anyhow::Result<ExportStatus> {
+    if export_paths
+        .iter()
+        .any(|export_path| !export_path.is_file())
+    {
+        return Ok(ExportStatus::Missing);
+    }
+    let comic_download_dir = comic
+        .comic_download_dir
+        .as_ref()
+        .context("`comic_download_dir` field is `None`")?;
+
+    let source_manifest = SourceManifest::read(app, comic_download_dir, format)?;
+    let is_current = export_paths
+        .iter()
+        .all(|export_path| SourceManifest::load(export_path).as_ref() == Some(&source_manifest));
+    let status = if is_current {
+        ExportStatus::Current
+    } else {
+        ExportStatus::Stale
+    };
+    Ok(status)
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportStatusReport {
+    pub missing: Vec<ExportStatusEntry>,
+    pub stale: Vec<ExportStatusEntry>,
+    /// Exports and source manifests in `export_dir` that don't belong to any downloaded comic
+    pub orphaned: Vec<PathBuf>,
+    /// Comics whose exports couldn't be checked, the other comics are still checked
+    pub failed: Vec<FailedExportStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedExportStatus {
+    pub id: i32,
+    pub title: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportStatusEntry {
+    pub id: i32,
+    pub title: String,
+    pub format: ExportFormat,
+    /// More than one if the export is split into volumes
+    pub paths: Vec<PathBuf>,
+}
+
+/// Check the exports of all downloaded comics in `formats`, and look for exports in `export_dir` without a comic
+pub fn get_export_status_report(
+    app: &AppHandle,
+    formats: &[ExportFormat],
+) -> anyhow::Result<ExportStatusReport> {
+    let (download_dir, export_dir) = {
+        let config = app.state::<RwLock<Config>>();
+        let config = config.read();
+        (config.download_dir.clone(), config.export_dir.clone())
+    };
+
+    let mut report = ExportStatusReport::default();
+    let mut known_paths = HashSet::new();
+    for comic in library::get_downloaded_comics(&download_dir) {
+        if let Err(err) = check_comic_exports(app, &comic, formats, &mut report, &mut known_paths) {
+            let err_title = format!("Failed to check the exports of `{}`", comic.title);
+            let string_chain = err.to_string_chain();
+            tracing::error!(err_title, message = string_chain);
+            report.failed.push(FailedExportStatus {
+                id: comic.id,
+                title: comic.title.clone(),
+                reason: string_chain,
+            });
+        }
+    }
+
+    report.orphaned = WalkDir::new(&export_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(walkdir::DirEntry::into_path)
+        .filter(|path| is_export_file(path) && !known_paths.contains(path))
+        .collect();
+    report.orphaned.sort();
+
+    Ok(report)
+}
+
+/// Add the exports of `comic` in `formats` that are missing or stale to `report`,
+/// and all paths its exports may have to `known_paths`
+fn check_comic_exports(
+    app: &AppHandle,
+    comic: &Comic,
+    formats: &[ExportFormat],
+    report: &mut ExportStatusReport,
+    known_paths: &mut HashSet<PathBuf>,
+) -> anyhow::Result<()> {
+    known_paths.extend(get_export_paths(app, comic)?);
+
+    for &format in formats {
+        let paths = get_export_part_paths(app, comic, format)?;
+        let extension = format.extension();
+        let status = get_export_status(app, comic, format, &paths).context(format!(
+            "Failed to get {extension} export status of `{}`",
+            comic.title
+        ))?;
+        let entry = ExportStatusEntry {
+            id: comic.id,
+            title: comic.title.clone(),
+            format,
+            paths,
+        };
+        match status {
+            ExportStatus::Missing => report.missing.push(entry),
+            ExportStatus::Stale => report.stale.push(entry),
+            ExportStatus::Current => {}
+        }
+    }
+    Ok(())
+}
+
+/// Whether `path` looks like something an export or a sync produces, other files in `export_dir` are left alone.
+/// An unfinished copy `<export>.part` and the sync state file never belong to a comic
+fn is_export_file(path: &Path) -> bool {
+    let filename = path
+        .file_name()
+        .map(|filename| filename.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if filename.ends_with(SOURCE_MANIFEST_SUFFIX) || filename == SYNC_STATE_FILENAME {
+        return true;
+    }
+    let filename = filename.strip_suffix(PARTIAL_SUFFIX).unwrap_or(&filename);
+    ExportFormat::ALL
+        .iter()
+        .any(|format| filename.ends_with(&format!(".{}", format.extension())))
+}
@@ -0,0 +1,374 @@
+mod batch;
+mod cancel;
+mod device_profile;
+mod epub;
+mod incremental;
+mod page_pool;
+mod pdf;
+mod pdf_writer;
+mod split;
+mod spread;
+mod sync;
+
+use page_pool::for_each_prepared_page;
+use spread::{export_images, ExportImage, SpreadLayout};
+
+pub use batch::*;
+pub use cancel::*;
+pub use epub::*;
+pub use incremental::*;
+pub use pdf::*;
+pub use split::*;
+pub use sync::*;
+
+use std::{collections::BTreeMap, io::Write, path::PathBuf};
+
+use anyhow::{anyhow, Context};
+use chrono::{Datelike, Timelike};
+use parking_lot::RwLock;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    config::Config,
+    events::ExportCbzEvent,
+    extensions::AnyhowErrorToStringChain,
+    library::{self, ComicStorage},
+    types::{CbzCompression, Comic, ExportFormat},
+};
+
+/// Export `comic` to `format` with the exporter of that format
+///
+/// `event_uuid` identifies the export in its events and in `ExportCancellations`
+pub fn export(
+    app: &AppHandle,
+    comic: &Comic,
+    format: ExportFormat,
+    event_uuid: &str,
+) -> anyhow::Result<()> {
+    match format {
+        ExportFormat::Cbz => cbz(app, comic, event_uuid),
+        ExportFormat::Pdf => pdf(app, comic, event_uuid),
+        ExportFormat::Epub => epub(app, comic, event_uuid),
+    }
+}
+
+/// Get the paths of all files that exporting `comic` may produce, whether they exist or not.
+/// This includes the volumes of split exports and the source manifest of every file
+pub fn get_export_paths(app: &AppHandle, comic: &Comic) -> anyhow::Result<Vec<PathBuf>> {
+    let mut export_paths = Vec::new();
+    for format in ExportFormat::ALL {
+        for export_path in get_export_part_paths(app, comic, format)? {
+            export_paths.push(source_manifest_path(&export_path));
+            export_paths.push(export_path);
+        }
+    }
+    Ok(export_paths)
+}
+
+/// Get the path of the file that exporting `comic` to `format` produces, whether it exists or not
+pub fn get_export_path(
+    app: &AppHandle,
+    comic: &Comic,
+    format: ExportFormat,
+) -> anyhow::Result<PathBuf> {
+    let export_dir = comic
+        .get_comic_export_dir(app)
+        .context("Failed to get comic export directory")?;
+    let download_dir_name = comic
+        .get_comic_download_dir_name()
+        .context("Failed to get comic download directory name")?;
+    let extension = format.extension();
+
+    Ok(export_dir.join(format!("{download_dir_name}.{extension}")))
+}
+
+/// Get the pages of `comic_storage` in reading order, a comic without pages is refused.
+/// Exporting a comic whose images were deleted after auto export would replace that export with an empty one
+///
+/// The parts of split exports and the spread layout are computed from `Comic::files`,
+/// so a comic whose stored pages don't match its files is refused too
+fn get_export_page_names(
+    comic_storage: &ComicStorage,
+    comic: &Comic,
+) -> anyhow::Result<Vec<String>> {
+    let comic_title = &comic.title;
+    if comic.images_deleted == Some(true) {
+        return Err(anyhow!(
+            "The images of `{comic_title}` were deleted after auto export, it has to be downloaded again to export it"
+        ));
+    }
+    let page_names = comic_storage.page_names()?;
+    if page_names.is_empty() {
+        return Err(anyhow!("`{comic_title}` has no pages to export"));
+    }
+    if page_names.len() != comic.files.len() {
+        return Err(anyhow!(
+            "`{comic_title}` has {} pages stored but {} in its metadata, it has to be downloaded again",
+            page_names.len(),
+            comic.files.len()
+        ));
+    }
+    Ok(page_names)
+}
+
+struct CbzEventGuard {
+    uuid: String,
+    app: AppHandle,
+    success: bool,
+    cancel_token: CancelToken,
+    /// Written to `<path>.part` first, which is deleted if the export doesn't succeed so an earlier export is kept
+    output_paths: Vec<PathBuf>,
+}
+
+impl Drop for CbzEventGuard {
+    fn drop(&mut self) {
+        if self.success {
+            let _ = ExportCbzEvent::End {
+                uuid: self.uuid.clone(),
+            }
+            .emit(&self.app);
+            return;
+        }
+
+        remove_unfinished_output(&self.output_paths);
+        if self.cancel_token.is_cancelled() {
+            let _ = ExportCbzEvent::Cancelled {
+                uuid: self.uuid.clone(),
+            }
+            .emit(&self.app);
+        } else {
+            let _ = ExportCbzEvent::Error {
+                uuid: self.uuid.clone(),
+            }
+            .emit(&self.app);
+        }
+    }
+}
+#[allow(clippy::cast_possible_wrap)]
+#[allow(clippy::cast_possible_truncation)]
+pub fn cbz(app: &AppHandle, comic: &Comic, event_uuid: &str) -> anyhow::Result<()> {
+    let comic_title = &comic.title;
+    let event_uuid = event_uuid.to_string();
+
+    let _ = ExportCbzEvent::Start {
+        uuid: event_uuid.clone(),
+        title: comic_title.clone(),
+    }
+    .emit(app);
+    // Event guard to ensure that the error event is sent if the function panics
+    let mut cbz_event_guard = CbzEventGuard {
+        uuid: event_uuid.clone(),
+        app: app.clone(),
+        success: false,
+        cancel_token: CancelToken::new(app, &event_uuid),
+        output_paths: Vec::new(),
+    };
+
+    let download_dir = comic
+        .comic_download_dir
+        .as_ref()
+        .context("`comic_download_dir` field is `None`")?;
+    let export_dir = comic
+        .get_comic_export_dir(app)
+        .context("Failed to get comic export directory")?;
+    // Read before exporting, so that pages changed during the export make it stale
+    let source_manifest = SourceManifest::read(app, download_dir, ExportFormat::Cbz)?;
+    // Ensure export directory exists
+    std::fs::create_dir_all(&export_dir).context(format!(
+        "`{comic_title}` failed to create directory `{}`",
+        export_dir.display()
+    ))?;
+    let extension = ExportFormat::Cbz.extension();
+    let download_dir_name = &comic
+        .get_comic_download_dir_name()
+        .context("Failed to get comic download directory name")?;
+    let zip_path = export_dir.join(format!("{download_dir_name}.{extension}"));
+    let parts = get_export_parts_by_config(app, comic, ExportFormat::Cbz);
+    let (cbz_compression, device_profile, spread_handling, reading_direction) = {
+        let config = app.state::<RwLock<Config>>();
+        let config = config.read();
+        (
+            config.cbz_compression,
+            config.device_profile.clone(),
+            config.spread_handling,
+            config.reading_direction,
+        )
+    };
+    // The comic may be stored as a folder or as a CBZ archive, pages are sorted so that they are in reading order
+    let mut comic_storage = ComicStorage::open(download_dir)?;
+    let page_names = get_export_page_names(&comic_storage, comic)?;
+    let file_options = cbz_file_options(comic, cbz_compression);
+    let total = page_names.len() as u32;
+    let mut current = 0;
+    for part in &parts {
+        let part_path = part.path(&zip_path);
+        let spread_layout =
+            SpreadLayout::new(&part.comic(comic), spread_handling, reading_direction);
+        // Generate ComicInfo and serialize it to xml
+        let mut comic_info = part.comic_info(comic, &spread_layout);
+        if device_profile.is_some() {
+            // The sizes in `Comic::files` are the sizes before the device profile scales the pages
+            for page in &mut comic_info.pages.pages {
+                page.image_width = None;
+                page.image_height = None;
+            }
+        }
+        let comic_info_xml = comic_info.to_xml().context(format!(
+            "`{comic_title}` failed to serialize `ComicInfo.xml`"
+        ))?;
+        // Create cbz file, it replaces the earlier export only once every part is finished
+        cbz_event_guard.output_paths.push(part_path.clone());
+        let partial_path = partial_path_of(&part_path);
+        let zip_file = std::fs::File::create(&partial_path).context(format!(
+            "`{comic_title}` failed to create file `{}`",
+            partial_path.display()
+        ))?;
+        let mut zip_writer = ZipWriter::new(zip_file);
+        // Write ComicInfo.xml into cbz
+        zip_writer
+            .start_file("ComicInfo.xml", file_options)
+            .context(format!(
+                "`{comic_title}` failed to create `ComicInfo.xml` in `{}`",
+                part_path.display()
+            ))?;
+        zip_writer
+            .write_all(comic_info_xml.as_bytes())
+            .context(format!("`{comic_title}` failed to write `ComicInfo.xml`"))?;
+        // Write the pages of this part into cbz, with spreads handled and processed for the device profile if there is one
+        let mut hashes = BTreeMap::new();
+        let part_page_names = &page_names[part.pages.clone()];
+        let prepare = |index: usize, page_path: &str, buffer: Vec<u8>| {
+            let page_name = &part_page_names[index];
+            let transforms = spread_layout.page_transforms(index);
+            export_images(page_name, buffer, transforms, device_profile.as_ref())
+                .context(format!("Failed to prepare `{page_path}` for export"))
+        };
+        let write = |_: &str, export_images: Vec<ExportImage>| -> anyhow::Result<()> {
+            for export_image in export_images {
+                let image_name = &export_image.name;
+                zip_writer
+                    .start_file(image_name, file_options)
+                    .context(format!(
+                        "`{comic_title}` failed to create `{image_name}` in `{}`",
+                        part_path.display()
+                    ))?;
+                zip_writer.write_all(&export_image.data).context(format!(
+                    "`{comic_title}` failed to write `{image_name}` to `{}`",
+                    part_path.display()
+                ))?;
+                hashes.insert(export_image.name, library::sha256_hex(&export_image.data));
+            }
+
+            current += 1;
+            let _ = ExportCbzEvent::Progress {
+                uuid: event_uuid.clone(),
+                current,
+                total,
+            }
+            .emit(app);
+            Ok(())
+        };
+        for_each_prepared_page(
+            &mut comic_storage,
+            part_page_names,
+            &cbz_event_guard.cancel_token,
+            prepare,
+            write,
+        )?;
+        // The manifest of the pages as exported, which may differ from the pages in the library
+        zip_writer
+            .start_file(library::MANIFEST_FILENAME, file_options)
+            .context(format!(
+                "`{comic_title}` failed to create `{}` in `{}`",
+                library::MANIFEST_FILENAME,
+                part_path.display()
+            ))?;
+        zip_writer
+            .write_all(library::format_manifest(&hashes).as_bytes())
+            .context(format!(
+                "`{comic_title}` failed to write `{}`",
+                library::MANIFEST_FILENAME
+            ))?;
+
+        zip_writer.finish().context(format!(
+            "`{comic_title}` failed to close `{}`",
+            part_path.display()
+        ))?;
+    }
+    finish_output(&cbz_event_guard.output_paths, &source_manifest)?;
+    // Set success to true to ensure that the end event is sent
+    cbz_event_guard.success = true;
+
+    Ok(())
+}
+
+/// Every entry gets the same options, with the gallery date as timestamp instead of the time of the export,
+/// so that exporting the same comic twice produces byte-identical archives
+fn cbz_file_options(comic: &Comic, cbz_compression: CbzCompression) -> SimpleFileOptions {
+    let compression_method = match cbz_compression {
+        CbzCompression::Store => CompressionMethod::Stored,
+        CbzCompression::Deflate => CompressionMethod::Deflated,
+    };
+    // Zip timestamps can only hold dates from 1980 to 2107, the default is 1980-01-01 00:00:00
+    #[allow(clippy::cast_possible_truncation)]
+    let last_modified_time = comic
+        .parse_date()
+        .and_then(|date| {
+            zip::DateTime::from_date_and_time(
+                u16::try_from(date.year()).ok()?,
+                date.month() as u8,
+                date.day() as u8,
+                date.hour() as u8,
+                date.minute() as u8,
+                date.second() as u8,
+            )
+            .ok()
+        })
+        .unwrap_or_default();
+
+    SimpleFileOptions::default()
+        .compression_method(compression_method)
+        .last_modified_time(last_modified_time)
+        .unix_permissions(0o644)
+}
+
+/// Move the finished `<path>.part` of every output over its earlier export and save its source manifest
+fn finish_output(output_paths: &[PathBuf], source_manifest: &SourceManifest) -> anyhow::Result<()> {
+    for output_path in output_paths {
+        let partial_path = partial_path_of(output_path);
+        std::fs::rename(&partial_path, output_path).context(format!(
+            "Failed to rename `{}` to `{}`",
+            partial_path.display(),
+            output_path.display()
+        ))?;
+        source_manifest.save(output_path)?;
+    }
+    Ok(())
+}
+
+/// Delete the partial output of an export that failed or was cancelled, the earlier export is left as it is
+fn remove_unfinished_output(output_paths: &[PathBuf]) {
+    for path in output_paths
+        .iter()
+        .map(|output_path| partial_path_of(output_path))
+    {
+        if !path.exists() {
+            continue;
+        }
+        if let Err(err) = std::fs::remove_file(&path).map_err(anyhow::Error::from) {
+            let err_title = format!("Failed to delete unfinished `{}`", path.display());
+            let string_chain = err.to_string_chain();
+            tracing::error!(err_title, message = string_chain);
+        }
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,65 @@
+use std::{num::NonZeroUsize, thread};
+
+use anyhow::{anyhow, Context};
+
+use crate::library::ComicStorage;
+
+use super::CancelToken;
+
+/// Upper limit of pages that are decoded and encoded at the same time, more threads rarely help because of disk IO
+const MAX_EXPORT_WORKERS: usize = 8;
+
+/// Run `prepare` on the pages `page_names` of `comic_storage` in parallel, then `consume` the results in page order
+///
+/// Pages are handled one chunk at a time and a chunk has as many pages as there are workers,
+/// so at most one chunk of images is in memory, no matter how many pages the comic has.
+/// `prepare` gets the index of a page in `page_names`, its display path and its content
+pub fn for_each_prepared_page<T: Send>(
+    comic_storage: &mut ComicStorage,
+    page_names: &[String],
+    cancel_token: &CancelToken,
+    prepare: impl Fn(usize, &str, Vec<u8>) -> anyhow::Result<T> + Sync,
+    mut consume: impl FnMut(&str, T) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let worker_count = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(MAX_EXPORT_WORKERS);
+
+    for (chunk_index, chunk) in page_names.chunks(worker_count).enumerate() {
+        cancel_token.check()?;
+        // Pages are read one by one, because a CBZ archive can't be read from several threads
+        let mut buffers = Vec::with_capacity(chunk.len());
+        for (i, page_name) in chunk.iter().enumerate() {
+            let page_path = comic_storage.display_path(page_name);
+            let buffer = comic_storage
+                .read(page_name)
+                .context(format!("Failed to read `{page_path}` into buffer"))?;
+            buffers.push((chunk_index * worker_count + i, page_path, buffer));
+        }
+
+        let prepare = &prepare;
+        let prepared_pages = thread::scope(|scope| {
+            let handles: Vec<_> = buffers
+                .into_iter()
+                .map(|(index, page_path, buffer)| {
+                    scope.spawn(move || prepare(index, &page_path, buffer))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| -> anyhow::Result<T> {
+                    handle
+                        .join()
+                        .map_err(|_| anyhow!("The thread preparing a page panicked"))?
+                })
+                .collect::<anyhow::Result<Vec<T>>>()
+        })?;
+
+        for (page_name, prepared_page) in chunk.iter().zip(prepared_pages) {
+            cancel_token.check()?;
+            consume(page_name, prepared_page)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,493 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use image::{codecs::jpeg::JpegEncoder, DynamicImage, GenericImageView, ImageEncoder, ImageFormat};
+use lopdf::{
+    content::{Content, Operation},
+    dictionary, text_string, Dictionary, Object, ObjectId, Stream,
+};
+use parking_lot::RwLock;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+
+use crate::{
+    config::Config,
+    events::ExportPdfEvent,
+    image_codec,
+    library::ComicStorage,
+    types::{
+        Comic, DeviceProfile, ExportFormat, PdfImageEncoding, ReadingDirection, SpreadHandling, Tag,
+    },
+};
+
+use super::{
+    escape_xml, finish_output, get_export_page_names, get_export_parts_by_config,
+    page_pool::for_each_prepared_page,
+    partial_path_of,
+    pdf_writer::PdfWriter,
+    remove_unfinished_output,
+    spread::{export_images, PageTransform, SpreadLayout},
+    CancelToken, SourceManifest,
+};
+
+struct PdfEventGuard {
+    uuid: String,
+    app: AppHandle,
+    success: bool,
+    cancel_token: CancelToken,
+    /// Written to `<path>.part` first, which is deleted if the export doesn't succeed so an earlier export is kept
+    output_paths: Vec<PathBuf>,
+}
+
+impl Drop for PdfEventGuard {
+    fn drop(&mut self) {
+        let uuid = self.uuid.clone();
+
+        if !self.success {
+            remove_unfinished_output(&self.output_paths);
+        }
+        let _ = if self.success {
+            ExportPdfEvent::End { uuid }.emit(&self.app)
+        } else if self.cancel_token.is_cancelled() {
+            ExportPdfEvent::Cancelled { uuid }.emit(&self.app)
+        } else {
+            ExportPdfEvent::Error { uuid }.emit(&self.app)
+        };
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+pub fn pdf(app: &AppHandle, comic: &Comic, event_uuid: &str) -> anyhow::Result<()> {
+    let comic_title = &comic.title;
+    let event_uuid = event_uuid.to_string();
+
+    let _ = ExportPdfEvent::Start {
+        uuid: event_uuid.clone(),
+        title: comic_title.clone(),
+    }
+    .emit(app);
+
+    // Event guard to ensure that the error event is sent if the function panics
+    let mut pdf_event_guard = PdfEventGuard {
+        uuid: event_uuid.clone(),
+        app: app.clone(),
+        success: false,
+        cancel_token: CancelToken::new(app, &event_uuid),
+        output_paths: Vec::new(),
+    };
+
+    let download_dir = comic
+        .comic_download_dir
+        .as_ref()
+        .context("`comic_download_dir` field is `None`")?;
+    let export_dir = comic
+        .get_comic_export_dir(app)
+        .context("Failed to get comic export directory")?;
+    // Ensure export directory exists
+    std::fs::create_dir_all(&export_dir).context(format!(
+        "Failed to create directory `{}`",
+        export_dir.display()
+    ))?;
+    // Create PDF
+    let extension = ExportFormat::Pdf.extension();
+    let download_dir_name = &comic
+        .get_comic_download_dir_name()
+        .context("Failed to get comic download directory name")?;
+    let pdf_path = export_dir.join(format!("{download_dir_name}.{extension}"));
+    let pdf_options = PdfOptions::from(&*app.state::<RwLock<Config>>().read());
+    // Read before exporting, so that pages changed during the export make it stale
+    let source_manifest = SourceManifest::read(app, download_dir, ExportFormat::Pdf)?;
+    // The comic may be stored as a folder or as a CBZ archive
+    let mut comic_storage = ComicStorage::open(download_dir)?;
+    let page_names = get_export_page_names(&comic_storage, comic)?;
+    let total = page_names.len() as u32;
+    let mut current = 0;
+    for part in get_export_parts_by_config(app, comic, ExportFormat::Pdf) {
+        let part_path = part.path(&pdf_path);
+        let part_page_names = &page_names[part.pages.clone()];
+        pdf_event_guard.output_paths.push(part_path.clone());
+        let on_page_written = || {
+            current += 1;
+            let _ = ExportPdfEvent::Progress {
+                uuid: event_uuid.clone(),
+                current,
+                total,
+            }
+            .emit(app);
+        };
+        let part_comic = part.comic(comic);
+        let spread_layout = SpreadLayout::new(
+            &part_comic,
+            pdf_options.spread_handling,
+            pdf_options.reading_direction,
+        );
+        create_pdf(
+            &part_comic,
+            &spread_layout,
+            &mut comic_storage,
+            part_page_names,
+            &partial_path_of(&part_path),
+            &pdf_options,
+            &pdf_event_guard.cancel_token,
+            on_page_written,
+        )
+        .context(format!("Failed to create `{}`", part_path.display()))?;
+    }
+    finish_output(&pdf_event_guard.output_paths, &source_manifest)?;
+    // Set success to true to ensure that the end event is sent
+    pdf_event_guard.success = true;
+
+    Ok(())
+}
+
+/// The settings in `Config` that affect PDF export
+struct PdfOptions {
+    reading_direction: ReadingDirection,
+    image_encoding: PdfImageEncoding,
+    jpeg_quality: u8,
+    device_profile: Option<DeviceProfile>,
+    spread_handling: SpreadHandling,
+}
+
+impl From<&Config> for PdfOptions {
+    fn from(config: &Config) -> Self {
+        PdfOptions {
+            reading_direction: config.reading_direction,
+            image_encoding: config.pdf_image_encoding,
+            jpeg_quality: config.pdf_jpeg_quality.clamp(1, 100),
+            device_profile: config.device_profile.clone(),
+            spread_handling: config.spread_handling,
+        }
+    }
+}
+
+/// A page whose image is ready to be written into the PDF
+struct PreparedPage {
+    width: u32,
+    height: u32,
+    image_stream: Stream,
+}
+
+/// Create a PDF with the pages `page_names` of `comic_storage` and save it to `pdf_path`
+///
+/// Pages are prepared on the bounded worker pool and written to disk as soon as their chunk is done.
+/// A split spread becomes two PDF pages. `on_page_written` is called after every page of `page_names`
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::too_many_arguments)]
+fn create_pdf(
+    comic: &Comic,
+    spread_layout: &SpreadLayout,
+    comic_storage: &mut ComicStorage,
+    page_names: &[String],
+    pdf_path: &Path,
+    pdf_options: &PdfOptions,
+    cancel_token: &CancelToken,
+    mut on_page_written: impl FnMut(),
+) -> anyhow::Result<()> {
+    // Bookmarks have to point to the pages of the PDF, which differ from the pages of the comic if spreads are split
+    let comic = &spread_layout.comic(comic);
+    let mut pdf_writer = PdfWriter::create(pdf_path)?;
+    // The page tree is written last, when all of its kids are known
+    let pages_id = pdf_writer.reserve_id();
+    let mut page_ids = Vec::with_capacity(page_names.len());
+
+    for_each_prepared_page(
+        comic_storage,
+        page_names,
+        cancel_token,
+        |index, page_path, buffer| {
+            let transforms = spread_layout.page_transforms(index);
+            prepare_pages(
+                &page_names[index],
+                page_path,
+                buffer,
+                transforms,
+                pdf_options,
+            )
+        },
+        |_, prepared_pages| {
+            for prepared_page in prepared_pages {
+                let page_id = write_page(&mut pdf_writer, pages_id, prepared_page)?;
+                page_ids.push(page_id);
+            }
+            on_page_written();
+            Ok(())
+        },
+    )?;
+    // Write "Pages" now that all of its kids are written
+    let pages_dict = dictionary! {
+        "Type" => "Pages",
+        "Count" => page_ids.len() as u32,
+        "Kids" => page_ids.iter().copied().map(Object::Reference).collect::<Vec<_>>(),
+    };
+    pdf_writer.write_object(pages_id, &Object::Dictionary(pages_dict))?;
+    // Create a new "Catalog" object and add the "Pages" object to it
+    let mut catalog = dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    };
+    if let Some(outline_id) = write_outline(&mut pdf_writer, comic, &page_ids)? {
+        catalog.set("Outlines", outline_id);
+        catalog.set("PageMode", "UseOutlines");
+    }
+    if pdf_options.reading_direction == ReadingDirection::RightToLeft {
+        catalog.set("ViewerPreferences", dictionary! { "Direction" => "R2L" });
+    }
+    // XMP is what newer readers look at, it stays uncompressed so that tools can find it without parsing the PDF
+    let metadata_stream = Stream::new(
+        dictionary! {
+            "Type" => "Metadata",
+            "Subtype" => "XML",
+        },
+        render_xmp(comic).into_bytes(),
+    )
+    .with_compression(false);
+    let metadata_id = pdf_writer.add_object(&Object::Stream(metadata_stream))?;
+    catalog.set("Metadata", metadata_id);
+    let catalog_id = pdf_writer.add_object(&Object::Dictionary(catalog))?;
+    let info_id = pdf_writer.add_object(&Object::Dictionary(create_pdf_info(comic)))?;
+
+    pdf_writer.finish(catalog_id, info_id)?;
+    Ok(())
+}
+
+/// Turn the page into the image streams of its PDF pages, this is where the CPU time of the export goes
+///
+/// Spreads are handled and the device profile is applied first, each PDF page gets the size of its image
+fn prepare_pages(
+    page_name: &str,
+    page_path: &str,
+    buffer: Vec<u8>,
+    transforms: &[PageTransform],
+    pdf_options: &PdfOptions,
+) -> anyhow::Result<Vec<PreparedPage>> {
+    let export_images = export_images(
+        page_name,
+        buffer,
+        transforms,
+        pdf_options.device_profile.as_ref(),
+    )
+    .context(format!("Failed to prepare `{page_path}` for export"))?;
+
+    let mut prepared_pages = Vec::with_capacity(export_images.len());
+    for export_image in export_images {
+        let image_stream = create_image_stream(export_image.data, pdf_options)
+            .context(format!("Failed to create image stream for `{page_path}`"))?;
+        prepared_pages.push(PreparedPage {
+            width: export_image.width,
+            height: export_image.height,
+            image_stream,
+        });
+    }
+    Ok(prepared_pages)
+}
+
+/// Write the image, the content stream and the page object of `prepared_page`, return the id of the page object
+fn write_page(
+    pdf_writer: &mut PdfWriter,
+    pages_id: ObjectId,
+    prepared_page: PreparedPage,
+) -> anyhow::Result<ObjectId> {
+    let PreparedPage {
+        width,
+        height,
+        image_stream,
+    } = prepared_page;
+    let img_id = pdf_writer.add_object(&Object::Stream(image_stream))?;
+    // Image name for the Do operation to display the image on the page
+    let img_name = format!("X{}", img_id.0);
+    // Used to set image position and size on the page
+    let cm_operation = Operation::new(
+        "cm",
+        vec![
+            width.into(),
+            0.into(),
+            0.into(),
+            height.into(),
+            0.into(),
+            0.into(),
+        ],
+    );
+    // Used to display the image
+    let do_operation = Operation::new("Do", vec![Object::Name(img_name.as_bytes().to_vec())]);
+    // Create a page, set the image position and size, and then display the image
+    // Since we're creating a PDF from scratch, there's no need to use q and Q operations to save and restore graphics state
+    let content = Content {
+        operations: vec![cm_operation, do_operation],
+    };
+    let content_stream = Stream::new(dictionary! {}, content.encode()?);
+    let content_id = pdf_writer.add_object(&Object::Stream(content_stream))?;
+    // The Do operation can only reference XObjects by their name in the page resources
+    let page_dict = dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "Contents" => content_id,
+        "MediaBox" => vec![0.into(), 0.into(), width.into(), height.into()],
+        "Resources" => dictionary! {
+            "XObject" => dictionary! {
+                img_name.as_str() => img_id,
+            },
+        },
+    };
+    pdf_writer.add_object(&Object::Dictionary(page_dict))
+}
+
+/// Write a bookmark for the first page of every scene, return the id of the outline if there is any bookmark
+///
+/// `scene_indexes` are 0-based page indexes
+fn write_outline(
+    pdf_writer: &mut PdfWriter,
+    comic: &Comic,
+    page_ids: &[ObjectId],
+) -> anyhow::Result<Option<ObjectId>> {
+    let destinations: Vec<(String, ObjectId)> = comic
+        .scene_indexes
+        .iter()
+        .filter_map(|scene_index| usize::try_from(*scene_index).ok())
+        .filter_map(|index| page_ids.get(index).copied())
+        .enumerate()
+        .map(|(i, page_id)| (format!("Scene {}", i + 1), page_id))
+        .collect();
+    if destinations.is_empty() {
+        return Ok(None);
+    }
+
+    let outline_id = pdf_writer.reserve_id();
+    let item_ids: Vec<ObjectId> = destinations
+        .iter()
+        .map(|_| pdf_writer.reserve_id())
+        .collect();
+    for (i, (title, page_id)) in destinations.iter().enumerate() {
+        let mut item = dictionary! {
+            "Title" => text_string(title),
+            "Parent" => outline_id,
+            "Dest" => vec![Object::Reference(*page_id), Object::Name(b"Fit".to_vec())],
+        };
+        if let Some(prev_id) = i.checked_sub(1).map(|prev| item_ids[prev]) {
+            item.set("Prev", prev_id);
+        }
+        if let Some(next_id) = item_ids.get(i + 1) {
+            item.set("Next", *next_id);
+        }
+        pdf_writer.write_object(item_ids[i], &Object::Dictionary(item))?;
+    }
+    #[allow(clippy::cast_possible_wrap)]
+    let outline = dictionary! {
+        "Type" => "Outlines",
+        "First" => item_ids[0],
+        "Last" => item_ids[item_ids.len() - 1],
+        "Count" => item_ids.len() as i64,
+    };
+    pdf_writer.write_object(outline_id, &Object::Dictionary(outline))?;
+
+    Ok(Some(outline_id))
+}
+
+/// Create an image XObject from the file content `buffer`. Formats that lopdf can embed are passed to it as they are,
+/// the others such as AVIF and JPEG XL are decoded and encoded again as `PdfOptions::image_encoding`
+fn create_image_stream(buffer: Vec<u8>, pdf_options: &PdfOptions) -> anyhow::Result<Stream> {
+    let format = image::guess_format(&buffer).ok();
+    if matches!(
+        format,
+        Some(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP)
+    ) {
+        return Ok(lopdf::xobject::image_from(buffer)?);
+    }
+
+    let img = image_codec::decode(&buffer)?;
+    let (width, height) = img.dimensions();
+    // PDF images have no alpha channel unless a soft mask is added, comic pages don't need transparency
+    let (color_space, pixels) = if img.color().has_color() {
+        ("DeviceRGB", DynamicImage::ImageRgb8(img.to_rgb8()))
+    } else {
+        ("DeviceGray", DynamicImage::ImageLuma8(img.to_luma8()))
+    };
+    let mut dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => width,
+        "Height" => height,
+        "ColorSpace" => color_space,
+        "BitsPerComponent" => 8,
+    };
+
+    let image_stream = match pdf_options.image_encoding {
+        PdfImageEncoding::Jpeg => {
+            let mut jpeg = Vec::new();
+            JpegEncoder::new_with_quality(&mut jpeg, pdf_options.jpeg_quality)
+                .write_image(pixels.as_bytes(), width, height, pixels.color().into())
+                .context("Failed to encode image as JPEG")?;
+            dict.set("Filter", "DCTDecode");
+            Stream::new(dict, jpeg)
+        }
+        PdfImageEncoding::Flate => {
+            let mut image_stream = Stream::new(dict, pixels.into_bytes());
+            image_stream
+                .compress()
+                .context("Failed to compress image stream")?;
+            image_stream
+        }
+    };
+
+    Ok(image_stream)
+}
+
+/// The document information dictionary, which is what PDF readers show in the document properties
+fn create_pdf_info(comic: &Comic) -> Dictionary {
+    let keywords = comic
+        .tags
+        .iter()
+        .map(Tag::name_with_namespace)
+        .collect::<Vec<String>>()
+        .join(", ");
+    let creation_date = chrono::Local::now().format("D:%Y%m%d%H%M%S").to_string();
+    dictionary! {
+        "Title" => text_string(&comic.title),
+        "Author" => text_string(&comic.artists.join(", ")),
+        "Subject" => text_string(&comic.type_field),
+        "Keywords" => text_string(&keywords),
+        "Creator" => text_string("hitomi-downloader"),
+        // Not a standard key, but readers show custom keys in the document properties as well
+        "Source" => text_string(&comic.gallery_url()),
+        "CreationDate" => Object::string_literal(creation_date),
+    }
+}
+
+/// The same metadata as `create_pdf_info` as an XMP packet, with the gallery URL as `dc:source`
+fn render_xmp(comic: &Comic) -> String {
+    let title = escape_xml(&comic.title);
+    let subject = escape_xml(&comic.type_field);
+    let source = escape_xml(&comic.gallery_url());
+    let creators: String = comic
+        .artists
+        .iter()
+        .map(|artist| format!("<rdf:li>{}</rdf:li>", escape_xml(artist)))
+        .collect();
+    let keywords: String = comic
+        .tags
+        .iter()
+        .map(|tag| {
+            format!(
+                "<rdf:li>{}</rdf:li>",
+                escape_xml(&tag.name_with_namespace())
+            )
+        })
+        .collect();
+    format!(
+        r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:pdf="http://ns.adobe.com/pdf/1.3/">
+      <dc:format>application/pdf</dc:format>
+      <dc:title><rdf:Alt><rdf:li xml:lang="x-default">{title}</rdf:li></rdf:Alt></dc:title>
+      <dc:creator><rdf:Seq>{creators}</rdf:Seq></dc:creator>
+      <dc:description><rdf:Alt><rdf:li xml:lang="x-default">{subject}</rdf:li></rdf:Alt></dc:description>
+      <dc:subject><rdf:Bag>{keywords}</rdf:Bag></dc:subject>
+      <dc:source>{source}</dc:source>
+      <pdf:Producer>hitomi-downloader</pdf:Producer>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#
+    )
+}
@@ -0,0 +1,198 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use lopdf::{dictionary, Dictionary, Object, ObjectId, Stream, StringFormat};
+
+/// Writes a PDF to disk one object at a time, so that only the object being written has to be in memory.
+/// The cross-reference table is written by `finish` at the end of the file
+///
+/// Objects may be written in any order, ids can be reserved with `reserve_id` before the object they refer to is written
+pub struct PdfWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// Number of bytes written so far, which is the offset of the next object
+    offset: u64,
+    /// Offset of every object, indexed by object number - 1, `None` if the id is reserved but not written yet
+    object_offsets: Vec<Option<u64>>,
+}
+
+impl PdfWriter {
+    pub fn create(path: &Path) -> anyhow::Result<PdfWriter> {
+        let file = File::create(path).context(format!("Failed to create `{}`", path.display()))?;
+        let mut pdf_writer = PdfWriter {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            offset: 0,
+            object_offsets: Vec::new(),
+        };
+        // The binary comment tells transfer tools that the file is not plain text
+        pdf_writer.write_bytes(b"%PDF-1.5\n%\xE2\xE3\xCF\xD3\n")?;
+        Ok(pdf_writer)
+    }
+
+    /// Reserve an id for an object that is written later
+    pub fn reserve_id(&mut self) -> ObjectId {
+        self.object_offsets.push(None);
+        #[allow(clippy::cast_possible_truncation)]
+        let number = self.object_offsets.len() as u32;
+        (number, 0)
+    }
+
+    /// Write `object` with an id from `reserve_id`
+    ///
+    /// A stream without `Filter` is Flate-encoded first, unless its compression is turned off with `Stream::with_compression`
+    pub fn write_object(&mut self, id: ObjectId, object: &Object) -> anyhow::Result<()> {
+        let index = (id.0 as usize)
+            .checked_sub(1)
+            .filter(|index| *index < self.object_offsets.len())
+            .context(format!("Object id `{}` was not reserved", id.0))?;
+        if self.object_offsets[index].is_some() {
+            return Err(anyhow!("Object `{}` was written twice", id.0));
+        }
+        self.object_offsets[index] = Some(self.offset);
+
+        let mut buffer = format!("{} {} obj\n", id.0, id.1).into_bytes();
+        match object {
+            Object::Stream(stream) if stream.allows_compression && !stream.dict.has(b"Filter") => {
+                let mut compressed_stream = stream.clone();
+                compressed_stream
+                    .compress()
+                    .context(format!("Failed to compress stream `{}`", id.0))?;
+                serialize_stream(&mut buffer, &compressed_stream);
+            }
+            _ => serialize_object(&mut buffer, object),
+        }
+        buffer.extend_from_slice(b"\nendobj\n");
+        self.write_bytes(&buffer)
+    }
+
+    /// Reserve an id for `object` and write it right away
+    pub fn add_object(&mut self, object: &Object) -> anyhow::Result<ObjectId> {
+        let id = self.reserve_id();
+        self.write_object(id, object)?;
+        Ok(id)
+    }
+
+    /// Write the cross-reference table and the trailer, every reserved id must have been written by now
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn finish(mut self, root_id: ObjectId, info_id: ObjectId) -> anyhow::Result<()> {
+        let xref_offset = self.offset;
+        let object_count = self.object_offsets.len() + 1;
+        let mut xref = format!("xref\n0 {object_count}\n0000000000 65535 f \n");
+        for (index, offset) in self.object_offsets.iter().enumerate() {
+            let offset = offset.context(format!("Object `{}` was never written", index + 1))?;
+            let _ = writeln!(xref, "{offset:010} 00000 n ");
+        }
+        self.write_bytes(xref.as_bytes())?;
+
+        let trailer = Object::Dictionary(dictionary! {
+            "Size" => object_count as i64,
+            "Root" => root_id,
+            "Info" => info_id,
+        });
+        let mut buffer = b"trailer\n".to_vec();
+        serialize_object(&mut buffer, &trailer);
+        buffer.extend_from_slice(format!("\nstartxref\n{xref_offset}\n%%EOF\n").as_bytes());
+        self.write_bytes(&buffer)?;
+
+        self.writer
+            .flush()
+            .context(format!("Failed to flush `{}`", self.path.display()))?;
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        self.writer
+            .write_all(bytes)
+            .context(format!("Failed to write to `{}`", self.path.display()))?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+fn serialize_object(buffer: &mut Vec<u8>, object: &Object) {
+    match object {
+        Object::Null => buffer.extend_from_slice(b"null"),
+        Object::Boolean(value) => buffer.extend_from_slice(value.to_string().as_bytes()),
+        Object::Integer(value) => buffer.extend_from_slice(value.to_string().as_bytes()),
+        Object::Real(value) => buffer.extend_from_slice(value.to_string().as_bytes()),
+        Object::Name(name) => serialize_name(buffer, name),
+        Object::String(text, format) => serialize_string(buffer, text, *format),
+        Object::Array(array) => {
+            buffer.push(b'[');
+            for (i, object) in array.iter().enumerate() {
+                if i > 0 {
+                    buffer.push(b' ');
+                }
+                serialize_object(buffer, object);
+            }
+            buffer.push(b']');
+        }
+        Object::Dictionary(dict) => serialize_dictionary(buffer, dict),
+        Object::Stream(stream) => serialize_stream(buffer, stream),
+        Object::Reference(id) => {
+            buffer.extend_from_slice(format!("{} {} R", id.0, id.1).as_bytes())
+        }
+    }
+}
+
+fn serialize_name(buffer: &mut Vec<u8>, name: &[u8]) {
+    buffer.push(b'/');
+    for &byte in name {
+        // Delimiters, white-space and bytes outside of `!`..=`~` must be written as `#` sequences
+        if b" \t\n\r\x0C()<>[]{}/%#".contains(&byte) || !(33..=126).contains(&byte) {
+            buffer.extend_from_slice(format!("#{byte:02X}").as_bytes());
+        } else {
+            buffer.push(byte);
+        }
+    }
+}
+
+fn serialize_string(buffer: &mut Vec<u8>, text: &[u8], format: StringFormat) {
+    match format {
+        StringFormat::Literal => {
+            buffer.push(b'(');
+            for &byte in text {
+                // Escaping every parenthesis is always valid, so there is no need to check if they are balanced
+                match byte {
+                    b'(' | b')' | b'\\' => buffer.extend_from_slice(&[b'\\', byte]),
+                    b'\r' => buffer.extend_from_slice(b"\\r"),
+                    _ => buffer.push(byte),
+                }
+            }
+            buffer.push(b')');
+        }
+        StringFormat::Hexadecimal => {
+            buffer.push(b'<');
+            for &byte in text {
+                buffer.extend_from_slice(format!("{byte:02X}").as_bytes());
+            }
+            buffer.push(b'>');
+        }
+    }
+}
+
+fn serialize_dictionary(buffer: &mut Vec<u8>, dict: &Dictionary) {
+    buffer.extend_from_slice(b"<<");
+    for (key, value) in dict.iter() {
+        serialize_name(buffer, key);
+        buffer.push(b' ');
+        serialize_object(buffer, value);
+    }
+    buffer.extend_from_slice(b">>");
+}
+
+#[allow(clippy::cast_possible_wrap)]
+fn serialize_stream(buffer: &mut Vec<u8>, stream: &Stream) {
+    let mut dict = stream.dict.clone();
+    dict.set("Length", stream.content.len() as i64);
+    serialize_dictionary(buffer, &dict);
+    buffer.extend_from_slice(b"\nstream\n");
+    buffer.extend_from_slice(&stream.content);
+    buffer.extend_from_slice(b"\nendstream");
+}
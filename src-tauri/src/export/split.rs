@@ -0,0 +1,169 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use parking_lot::RwLock;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    config::Config,
+    types::{Comic, ComicInfo, ExportFormat, ExportSplitMode},
+};
+
+use super::{get_export_path, spread::SpreadLayout};
+
+/// One volume of a split export, a comic that isn't split has a single part covering all pages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportPart {
+    /// 1-based
+    pub number: usize,
+    pub count: usize,
+    /// Indexes into the sorted pages of the comic
+    pub pages: Range<usize>,
+}
+
+impl ExportPart {
+    pub fn is_split(&self) -> bool {
+        self.count > 1
+    }
+
+    /// `Title - Vol.02` for the second volume, the title of the comic if it isn't split
+    pub fn title(&self, comic: &Comic) -> String {
+        if !self.is_split() {
+            return comic.title.clone();
+        }
+        format!("{} - Vol.{}", comic.title, self.volume())
+    }
+
+    /// `<export_path stem> - Vol.02.<extension>` for the second volume, `export_path` if the comic isn't split
+    pub fn path(&self, export_path: &Path) -> PathBuf {
+        if !self.is_split() {
+            return export_path.to_path_buf();
+        }
+        let stem = export_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let extension = export_path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_string())
+            .unwrap_or_default();
+        export_path.with_file_name(format!("{stem} - Vol.{}.{extension}", self.volume()))
+    }
+
+    /// The comic as this part shows it: the title has the volume suffix,
+    /// `files` and `scene_indexes` only cover the pages of this part
+    pub fn comic(&self, comic: &Comic) -> Comic {
+        if !self.is_split() {
+            return comic.clone();
+        }
+        let mut part_comic = comic.clone();
+        part_comic.title = self.title(comic);
+        part_comic.files = comic
+            .files
+            .get(self.pages.clone())
+            .map(<[_]>::to_vec)
+            .unwrap_or_default();
+        #[allow(clippy::cast_possible_truncation)]
+        #[allow(clippy::cast_possible_wrap)]
+        let (start, end) = (self.pages.start as i32, self.pages.end as i32);
+        part_comic.scene_indexes = comic
+            .scene_indexes
+            .iter()
+            .filter(|scene_index| (start..end).contains(scene_index))
+            .map(|scene_index| scene_index - start)
+            .collect();
+        part_comic
+    }
+
+    /// `Number` and `Count` tell readers that the parts belong together, `Series` stays the title of the comic.
+    /// `spread_layout` is the layout of `ExportPart::comic`, the pages are listed as the export has them
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn comic_info(&self, comic: &Comic, spread_layout: &SpreadLayout) -> ComicInfo {
+        let mut comic_info = spread_layout.comic_info(&self.comic(comic));
+        comic_info.series.clone_from(&comic.title);
+        comic_info.number = Some(self.number.to_string());
+        comic_info.count = self.count as i64;
+        comic_info
+    }
+
+    /// Zero-padded to the width of `count`, at least 2 digits, so that volumes sort by name
+    fn volume(&self) -> String {
+        let width = self.count.to_string().len().max(2);
+        format!("{:0width$}", self.number)
+    }
+}
+
+/// Split the pages of `comic` into parts according to `split_mode`
+///
+/// The parts are computed from `Comic::files`, so they are the same whether or not the pages are on disk
+pub fn get_export_parts(
+    comic: &Comic,
+    split_mode: ExportSplitMode,
+    pages_per_volume: u32,
+) -> Vec<ExportPart> {
+    let page_count = comic.files.len();
+    let mut starts: Vec<usize> = match split_mode {
+        ExportSplitMode::None => Vec::new(),
+        ExportSplitMode::Scenes => comic
+            .scene_indexes
+            .iter()
+            .filter_map(|scene_index| usize::try_from(*scene_index).ok())
+            .collect(),
+        ExportSplitMode::PageCount => {
+            let pages_per_volume = (pages_per_volume as usize).max(1);
+            (0..page_count).step_by(pages_per_volume).collect()
+        }
+    };
+    // The first part always starts at the first page, even if the first scene starts later
+    starts.push(0);
+    starts.retain(|start| *start < page_count.max(1));
+    starts.sort_unstable();
+    starts.dedup();
+
+    let count = starts.len();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, start)| ExportPart {
+            number: i + 1,
+            count,
+            pages: *start..starts.get(i + 1).copied().unwrap_or(page_count),
+        })
+        .collect()
+}
+
+/// Get the parts `comic` is split into when exported to `format` with the current config
+pub fn get_export_parts_by_config(
+    app: &AppHandle,
+    comic: &Comic,
+    format: ExportFormat,
+) -> Vec<ExportPart> {
+    let (split_mode, pages_per_volume) = {
+        let config = app.state::<RwLock<Config>>();
+        let config = config.read();
+        (config.export_split_mode, config.export_pages_per_volume)
+    };
+    // An EPUB is always one file
+    let split_mode = if format == ExportFormat::Epub {
+        ExportSplitMode::None
+    } else {
+        split_mode
+    };
+    get_export_parts(comic, split_mode, pages_per_volume)
+}
+
+/// Get the paths of the files exporting `comic` to `format` produces with the current config, whether they exist or not
+pub fn get_export_part_paths(
+    app: &AppHandle,
+    comic: &Comic,
+    format: ExportFormat,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let export_path = get_export_path(app, comic, format)?;
+    let part_paths = get_export_parts_by_config(app, comic, format)
+        .iter()
+        .map(|part| part.path(&export_path))
+        .collect();
+    Ok(part_paths)
+}
@@ -0,0 +1,250 @@
+use std::path::Path;
+
+use anyhow::Context;
+use image::{codecs::png::PngEncoder, DynamicImage, GenericImageView, ImageEncoder};
+
+use crate::{
+    hitomi::GalleryFiles,
+    image_codec,
+    types::{Comic, ComicInfo, DeviceProfile, ReadingDirection, SpreadHandling},
+};
+
+use super::device_profile::{process_image, ProcessedPage};
+
+/// What happens to one page of the comic during export
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PageTransform {
+    Keep,
+    RotateClockwise,
+    RotateCounterclockwise,
+    LeftHalf,
+    RightHalf,
+}
+
+impl PageTransform {
+    fn apply(self, img: DynamicImage) -> DynamicImage {
+        let (width, height) = img.dimensions();
+        match self {
+            PageTransform::Keep => img,
+            PageTransform::RotateClockwise => img.rotate90(),
+            PageTransform::RotateCounterclockwise => img.rotate270(),
+            PageTransform::LeftHalf => img.crop_imm(0, 0, width / 2, height),
+            PageTransform::RightHalf => img.crop_imm(width / 2, 0, width - width / 2, height),
+        }
+    }
+
+    /// The size of a `width` x `height` page after the transform
+    fn dimensions(self, width: i32, height: i32) -> (i32, i32) {
+        match self {
+            PageTransform::Keep => (width, height),
+            PageTransform::RotateClockwise | PageTransform::RotateCounterclockwise => {
+                (height, width)
+            }
+            PageTransform::LeftHalf => (width / 2, height),
+            PageTransform::RightHalf => (width - width / 2, height),
+        }
+    }
+
+    fn is_rotation(self) -> bool {
+        matches!(
+            self,
+            PageTransform::RotateClockwise | PageTransform::RotateCounterclockwise
+        )
+    }
+}
+
+/// A page that is wider than it is tall, found from the gallery metadata without decoding anything
+pub fn is_spread(file: &GalleryFiles) -> bool {
+    file.width > file.height
+}
+
+/// The transforms of every page of a comic, computed from `Comic::files` before any page is read
+#[derive(Debug, Clone)]
+pub struct SpreadLayout {
+    /// Indexed like `Comic::files`, a page turns into one exported image per transform
+    transforms: Vec<Vec<PageTransform>>,
+}
+
+impl SpreadLayout {
+    pub fn new(
+        comic: &Comic,
+        spread_handling: SpreadHandling,
+        reading_direction: ReadingDirection,
+    ) -> SpreadLayout {
+        let spread_transforms = match (spread_handling, reading_direction) {
+            (SpreadHandling::Keep, _) => vec![PageTransform::Keep],
+            (SpreadHandling::Split, ReadingDirection::LeftToRight) => {
+                vec![PageTransform::LeftHalf, PageTransform::RightHalf]
+            }
+            (SpreadHandling::Split, ReadingDirection::RightToLeft) => {
+                vec![PageTransform::RightHalf, PageTransform::LeftHalf]
+            }
+            // Rotating clockwise puts the left half at the top, counterclockwise the right half
+            (SpreadHandling::Rotate, ReadingDirection::LeftToRight) => {
+                vec![PageTransform::RotateClockwise]
+            }
+            (SpreadHandling::Rotate, ReadingDirection::RightToLeft) => {
+                vec![PageTransform::RotateCounterclockwise]
+            }
+        };
+        let transforms = comic
+            .files
+            .iter()
+            .map(|file| {
+                if is_spread(file) {
+                    spread_transforms.clone()
+                } else {
+                    vec![PageTransform::Keep]
+                }
+            })
+            .collect();
+        SpreadLayout { transforms }
+    }
+
+    /// The transforms of the page at `index`, a page without metadata is kept as it is
+    pub fn page_transforms(&self, index: usize) -> &[PageTransform] {
+        self.transforms
+            .get(index)
+            .map_or(&[PageTransform::Keep][..], Vec::as_slice)
+    }
+
+    /// The comic as the export shows it: `files` has one entry per exported image
+    /// and `scene_indexes` point to the first image of each scene
+    pub fn comic(&self, comic: &Comic) -> Comic {
+        let mut spread_comic = comic.clone();
+        spread_comic.files = comic
+            .files
+            .iter()
+            .zip(&self.transforms)
+            .flat_map(|(file, transforms)| {
+                transforms.iter().map(|transform| {
+                    let (width, height) = transform.dimensions(file.width, file.height);
+                    GalleryFiles {
+                        width,
+                        height,
+                        ..file.clone()
+                    }
+                })
+            })
+            .collect();
+        spread_comic.scene_indexes = comic
+            .scene_indexes
+            .iter()
+            .filter_map(|scene_index| {
+                let scene_index = usize::try_from(*scene_index).ok()?;
+                let image_index: usize =
+                    self.transforms.iter().take(scene_index).map(Vec::len).sum();
+                i32::try_from(image_index).ok()
+            })
+            .collect();
+        spread_comic
+    }
+
+    /// `ComicInfo` of `comic` as the export shows it, rotated spreads are still marked as `DoublePage`
+    pub fn comic_info(&self, comic: &Comic) -> ComicInfo {
+        let mut comic_info = ComicInfo::from(self.comic(comic));
+        let image_transforms = self.transforms.iter().flatten();
+        for (page, transform) in comic_info.pages.pages.iter_mut().zip(image_transforms) {
+            if transform.is_rotation() {
+                page.double_page = Some(true);
+            }
+        }
+        comic_info
+    }
+}
+
+/// One image of the export, made from a page of the comic
+pub struct ExportImage {
+    /// `0005.webp` for a page that is exported as it is, `0005-1.png` and `0005-2.png` for the halves of a split spread
+    pub name: String,
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Turn the page `page_name` with content `buffer` into the images of the export
+///
+/// A page that needs neither a transform nor `device_profile` is passed through without decoding.
+/// Otherwise it's processed with `device_profile`, or encoded as PNG if there is none, so nothing is lost
+pub fn export_images(
+    page_name: &str,
+    buffer: Vec<u8>,
+    transforms: &[PageTransform],
+    device_profile: Option<&DeviceProfile>,
+) -> anyhow::Result<Vec<ExportImage>> {
+    if transforms == [PageTransform::Keep] && device_profile.is_none() {
+        let (width, height) =
+            image_codec::dimensions(&buffer).context("Failed to get image dimensions")?;
+        let export_image = ExportImage {
+            name: page_name.to_string(),
+            data: buffer,
+            width,
+            height,
+        };
+        return Ok(vec![export_image]);
+    }
+
+    let img = image_codec::decode(&buffer)?;
+    let images: Vec<DynamicImage> = match transforms {
+        [transform] => vec![transform.apply(img)],
+        _ => transforms
+            .iter()
+            .map(|transform| transform.apply(img.clone()))
+            .collect(),
+    };
+    let stem = Path::new(page_name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut export_images = Vec::with_capacity(images.len());
+    for (i, img) in images.into_iter().enumerate() {
+        let (data, width, height, extension) = match device_profile {
+            Some(device_profile) => {
+                let processed_page = process_image(img, device_profile)
+                    .context(format!("Failed to process for `{}`", device_profile.name))?;
+                let extension = device_profile.image_format.extension();
+                let ProcessedPage {
+                    data,
+                    width,
+                    height,
+                } = processed_page;
+                (data, width, height, extension)
+            }
+            None => {
+                let (width, height) = img.dimensions();
+                (encode_png(&img)?, width, height, "png")
+            }
+        };
+        let filename = if transforms.len() > 1 {
+            format!("{stem}-{}.{extension}", i + 1)
+        } else {
+            format!("{stem}.{extension}")
+        };
+        export_images.push(ExportImage {
+            name: Path::new(page_name)
+                .with_file_name(filename)
+                .to_string_lossy()
+                .to_string(),
+            data,
+            width,
+            height,
+        });
+    }
+    Ok(export_images)
+}
+
+/// Encode `img` as PNG without loss, comic pages don't need transparency so the alpha channel is dropped
+pub fn encode_png(img: &DynamicImage) -> anyhow::Result<Vec<u8>> {
+    let (width, height) = img.dimensions();
+    let img = if img.color().has_color() {
+        DynamicImage::ImageRgb8(img.to_rgb8())
+    } else {
+        DynamicImage::ImageLuma8(img.to_luma8())
+    };
+    let mut data = Vec::new();
+    PngEncoder::new(&mut data)
+        .write_image(img.as_bytes(), width, height, img.color().into())
+        .context("Failed to encode image as PNG")?;
+    Ok(data)
+}
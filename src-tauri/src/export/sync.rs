@@ -0,0 +1,310 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+use walkdir::WalkDir;
+
+use crate::{
+    config::Config, events::SyncExportsEvent, extensions::AnyhowErrorToStringChain,
+    types::SyncTarget,
+};
+
+use super::{SourceFile, SOURCE_MANIFEST_SUFFIX};
+
+/// Name of the sync state file in the root of a target
+pub const SYNC_STATE_FILENAME: &str = ".hitomi-downloader-sync.json";
+/// Appended to the name of a file while it's being copied, so that an interrupted copy never looks finished
+pub const PARTIAL_SUFFIX: &str = ".part";
+/// The sync state is saved after this many copied files, so an interrupted sync only copies these again
+const SYNC_STATE_SAVE_INTERVAL: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub target: PathBuf,
+    /// Paths relative to `export_dir` and the target
+    pub copied: Vec<PathBuf>,
+    pub deleted: Vec<PathBuf>,
+    pub unchanged_count: u32,
+    pub failed: Vec<FailedSync>,
+    /// Set if the target couldn't be synced at all, e.g. because the device isn't mounted
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedSync {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// The files a target got from `export_dir`, as they were in `export_dir` when they were copied.
+/// It's saved every `SYNC_STATE_SAVE_INTERVAL` copied files, so an interrupted sync continues close to where it stopped
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncState {
+    /// Keyed by the path relative to `export_dir`, with `/` as separator
+    files: BTreeMap<String, SourceFile>,
+}
+
+impl SyncState {
+    /// An empty state if the target has none or it can't be parsed, which makes the next sync copy everything
+    fn load(target_dir: &Path) -> SyncState {
+        std::fs::read_to_string(target_dir.join(SYNC_STATE_FILENAME))
+            .ok()
+            .and_then(|state_json| serde_json::from_str(&state_json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, target_dir: &Path) -> anyhow::Result<()> {
+        let state_path = target_dir.join(SYNC_STATE_FILENAME);
+        let state_json =
+            serde_json::to_string_pretty(self).context("Failed to serialize sync state")?;
+        std::fs::write(&state_path, state_json)
+            .context(format!("Failed to write `{}`", state_path.display()))?;
+        Ok(())
+    }
+}
+
+/// Mirror `export_dir` to every directory in `Config::sync_targets`, one after another
+pub fn sync_exports(app: &AppHandle) -> Vec<SyncReport> {
+    let (export_dir, sync_targets) = {
+        let config = app.state::<RwLock<Config>>();
+        let config = config.read();
+        (config.export_dir.clone(), config.sync_targets.clone())
+    };
+
+    let mut reports = Vec::with_capacity(sync_targets.len());
+    for sync_target in sync_targets {
+        let mut report = SyncReport {
+            target: sync_target.path.clone(),
+            copied: Vec::new(),
+            deleted: Vec::new(),
+            unchanged_count: 0,
+            failed: Vec::new(),
+            error: None,
+        };
+        if let Err(err) = sync_target_dir(app, &export_dir, &sync_target, &mut report) {
+            let err_title = format!("Failed to sync exports to `{}`", sync_target.path.display());
+            let string_chain = err.to_string_chain();
+            tracing::error!(err_title, message = string_chain);
+            report.error = Some(string_chain);
+        }
+        reports.push(report);
+    }
+    reports
+}
+
+/// Copy the files of `export_dir` that are new or changed since the last sync to `sync_target`,
+/// then delete the files that were removed from `export_dir` if `SyncTarget::delete_removed` is set
+#[allow(clippy::cast_possible_truncation)]
+fn sync_target_dir(
+    app: &AppHandle,
+    export_dir: &Path,
+    sync_target: &SyncTarget,
+    report: &mut SyncReport,
+) -> anyhow::Result<()> {
+    let target_dir = &sync_target.path;
+    // The target is not created, an unmounted device would otherwise be synced to the empty mount point
+    if !target_dir.is_dir() {
+        return Err(anyhow!(
+            "`{}` does not exist or is not a directory, make sure the device is mounted",
+            target_dir.display()
+        ));
+    }
+    let mut sync_state = SyncState::load(target_dir);
+    let source_paths = get_source_paths(export_dir);
+    remove_partial_files(
+        target_dir,
+        source_paths.keys().chain(sync_state.files.keys()),
+    );
+
+    let uuid = uuid::Uuid::new_v4().to_string();
+    let total = source_paths.len() as u32;
+    let _ = SyncExportsEvent::Start {
+        uuid: uuid.clone(),
+        target: target_dir.clone(),
+        total,
+    }
+    .emit(app);
+
+    let mut unsaved_count = 0;
+    for (i, (relative_path, source_path)) in source_paths.iter().enumerate() {
+        let result = SourceFile::read(source_path).and_then(|source_file| {
+            let target_path = target_dir.join(relative_path);
+            let target_size = std::fs::metadata(&target_path).map(|metadata| metadata.len());
+            let is_unchanged = sync_state.files.get(relative_path) == Some(&source_file)
+                && target_size.is_ok_and(|size| size == source_file.size);
+            if is_unchanged {
+                report.unchanged_count += 1;
+                return Ok(());
+            }
+
+            copy_file(source_path, &target_path, source_file.size)?;
+            sync_state.files.insert(relative_path.clone(), source_file);
+            report.copied.push(PathBuf::from(relative_path));
+            unsaved_count += 1;
+            Ok(())
+        });
+        let is_failed = result.is_err();
+        if let Err(err) = result {
+            let err_title = format!("Failed to sync `{}`", source_path.display());
+            let string_chain = err.to_string_chain();
+            tracing::error!(err_title, message = string_chain);
+            report.failed.push(FailedSync {
+                path: PathBuf::from(relative_path),
+                reason: string_chain,
+            });
+        }
+        // Saved on errors as well, the device may be about to disappear
+        if unsaved_count >= SYNC_STATE_SAVE_INTERVAL || (is_failed && unsaved_count > 0) {
+            sync_state.save(target_dir)?;
+            unsaved_count = 0;
+        }
+
+        let _ = SyncExportsEvent::Progress {
+            uuid: uuid.clone(),
+            current: i as u32 + 1,
+            total,
+            failed: report.failed.len() as u32,
+        }
+        .emit(app);
+    }
+
+    if sync_target.delete_removed {
+        let removed_paths: Vec<String> = sync_state
+            .files
+            .keys()
+            .filter(|relative_path| !source_paths.contains_key(*relative_path))
+            .cloned()
+            .collect();
+        for relative_path in removed_paths {
+            let target_path = target_dir.join(&relative_path);
+            match std::fs::remove_file(&target_path) {
+                Ok(()) => remove_empty_parents(target_dir, &target_path),
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => {
+                    let err = anyhow!(err)
+                        .context(format!("Failed to delete `{}`", target_path.display()));
+                    report.failed.push(FailedSync {
+                        path: PathBuf::from(&relative_path),
+                        reason: err.to_string_chain(),
+                    });
+                    continue;
+                }
+            }
+            sync_state.files.remove(&relative_path);
+            report.deleted.push(PathBuf::from(relative_path));
+        }
+    }
+    sync_state.save(target_dir)?;
+
+    let _ = SyncExportsEvent::End { uuid }.emit(app);
+    Ok(())
+}
+
+/// The files of `export_dir` keyed by their path relative to it, source manifests and sync leftovers stay behind
+fn get_source_paths(export_dir: &Path) -> BTreeMap<String, PathBuf> {
+    WalkDir::new(export_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(walkdir::DirEntry::into_path)
+        .filter(|path| {
+            let path = path.to_string_lossy();
+            !path.ends_with(SOURCE_MANIFEST_SUFFIX)
+                && !path.ends_with(PARTIAL_SUFFIX)
+                && !path.ends_with(SYNC_STATE_FILENAME)
+        })
+        .filter_map(|path| {
+            let relative_path = path
+                .strip_prefix(export_dir)
+                .ok()?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            Some((relative_path, path))
+        })
+        .collect()
+}
+
+/// Delete the partial files an interrupted sync left behind for `relative_paths`,
+/// other `.part` files in the target don't come from a sync and are left alone
+fn remove_partial_files<'a>(target_dir: &Path, relative_paths: impl Iterator<Item = &'a String>) {
+    for relative_path in relative_paths {
+        let partial_path = partial_path_of(&target_dir.join(relative_path));
+        match std::fs::remove_file(&partial_path) {
+            Ok(()) => tracing::debug!("Removed stale `{}`", partial_path.display()),
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => {
+                let err_title = format!("Failed to remove stale `{}`", partial_path.display());
+                let string_chain = anyhow!(err).to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+            }
+        }
+    }
+}
+
+/// `<path>.part`
+pub fn partial_path_of(path: &Path) -> PathBuf {
+    let mut partial_path = OsString::from(path.as_os_str());
+    partial_path.push(PARTIAL_SUFFIX);
+    PathBuf::from(partial_path)
+}
+
+/// Copy `source_path` to `target_path` through a partial file, which only replaces `target_path` once its size is verified
+fn copy_file(source_path: &Path, target_path: &Path, expected_size: u64) -> anyhow::Result<()> {
+    if let Some(parent) = target_path.parent() {
+        std::fs::create_dir_all(parent)
+            .context(format!("Failed to create directory `{}`", parent.display()))?;
+    }
+    let partial_path = partial_path_of(target_path);
+
+    std::fs::copy(source_path, &partial_path).context(format!(
+        "Failed to copy `{}` to `{}`",
+        source_path.display(),
+        partial_path.display()
+    ))?;
+    let copied_size = std::fs::metadata(&partial_path)
+        .context(format!(
+            "Failed to get metadata of `{}`",
+            partial_path.display()
+        ))?
+        .len();
+    if copied_size != expected_size {
+        let _ = std::fs::remove_file(&partial_path);
+        return Err(anyhow!(
+            "`{}` has {copied_size} bytes after copying, expected {expected_size}",
+            partial_path.display()
+        ));
+    }
+    std::fs::rename(&partial_path, target_path).context(format!(
+        "Failed to rename `{}` to `{}`",
+        partial_path.display(),
+        target_path.display()
+    ))?;
+    Ok(())
+}
+
+/// Remove the directories between `path` and `target_dir` that are empty now, `target_dir` itself is kept
+fn remove_empty_parents(target_dir: &Path, path: &Path) {
+    for dir in path.ancestors().skip(1) {
+        if dir == target_dir || !dir.starts_with(target_dir) {
+            break;
+        }
+        // Fails if the directory isn't empty, which is where the removal stops
+        if std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
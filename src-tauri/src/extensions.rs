@@ -29,6 +29,11 @@ impl PathIsImg for std::path::Path {
         self.extension()
             .and_then(|ext| ext.to_str())
             .map(str::to_lowercase)
-            .is_some_and(|ext| matches!(ext.as_str(), "webp" | "avif"))
+            .is_some_and(|ext| {
+                matches!(
+                    ext.as_str(),
+                    "webp" | "avif" | "jxl" | "jpg" | "jpeg" | "png"
+                )
+            })
     }
 }
@@ -0,0 +1,83 @@
+use std::io::Cursor;
+
+use anyhow::{anyhow, Context};
+use byteorder::{BigEndian, ByteOrder};
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use jxl_oxide::integration::JxlDecoder;
+
+/// Signature of a bare JPEG XL codestream
+const JXL_CODESTREAM_SIGNATURE: &[u8] = &[0xFF, 0x0A];
+/// Signature of a JPEG XL codestream in an ISOBMFF container
+const JXL_CONTAINER_SIGNATURE: &[u8] = &[
+    0x00, 0x00, 0x00, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A,
+];
+/// Type of the AVIF property box that holds the width and height of an image
+const AVIF_ISPE_BOX_TYPE: &[u8] = b"ispe";
+
+/// Whether `data` is a JPEG XL image, the `image` crate doesn't recognize JPEG XL so it's checked by signature
+pub fn is_jxl(data: &[u8]) -> bool {
+    data.starts_with(JXL_CODESTREAM_SIGNATURE) || data.starts_with(JXL_CONTAINER_SIGNATURE)
+}
+
+fn is_avif(data: &[u8]) -> bool {
+    image::guess_format(data).is_ok_and(|format| format == ImageFormat::Avif)
+}
+
+/// AVIF is only decoded with the `avif` feature, which is on by default but needs libdav1d
+fn ensure_decodable(data: &[u8]) -> anyhow::Result<()> {
+    if is_avif(data) && !ImageFormat::Avif.reading_enabled() {
+        return Err(anyhow!(
+            "This build can't decode AVIF images, it has to be built with the `avif` feature"
+        ));
+    }
+    Ok(())
+}
+
+/// Decode `data` with the `image` crate, or with `jxl-oxide` if it's a JPEG XL image
+pub fn decode(data: &[u8]) -> anyhow::Result<DynamicImage> {
+    ensure_decodable(data)?;
+    if is_jxl(data) {
+        let decoder =
+            JxlDecoder::new(Cursor::new(data)).context("Failed to read JPEG XL header")?;
+        return DynamicImage::from_decoder(decoder).context("Failed to decode JPEG XL image");
+    }
+    image::load_from_memory(data).context("Failed to decode image")
+}
+
+/// Get the width and height of `data` from its header, without decoding the whole image
+///
+/// Pages that are passed through unchanged only need this, so it works for AVIF even without the `avif` feature
+pub fn dimensions(data: &[u8]) -> anyhow::Result<(u32, u32)> {
+    if is_avif(data) && !ImageFormat::Avif.reading_enabled() {
+        return avif_dimensions(data);
+    }
+    if is_jxl(data) {
+        let decoder =
+            JxlDecoder::new(Cursor::new(data)).context("Failed to read JPEG XL header")?;
+        return Ok(decoder.dimensions());
+    }
+    let dimensions = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .context("Failed to guess image format")?
+        .into_dimensions()
+        .context("Failed to read image header")?;
+    Ok(dimensions)
+}
+
+/// Read the width and height of an AVIF image from its first `ispe` property, which belongs to the primary image
+/// in AVIF files with a single image such as the ones hitomi serves
+fn avif_dimensions(data: &[u8]) -> anyhow::Result<(u32, u32)> {
+    let box_type_position = data
+        .windows(AVIF_ISPE_BOX_TYPE.len())
+        .position(|window| window == AVIF_ISPE_BOX_TYPE)
+        .context("Failed to find the `ispe` property of the AVIF image")?;
+    // The box type is followed by 4 bytes of version and flags, then the width and the height
+    let start = box_type_position + AVIF_ISPE_BOX_TYPE.len() + 4;
+    let extents = data
+        .get(start..start + 8)
+        .context("The `ispe` property of the AVIF image is truncated")?;
+    Ok((
+        BigEndian::read_u32(&extents[..4]),
+        BigEndian::read_u32(&extents[4..]),
+    ))
+}
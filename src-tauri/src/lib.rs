@@ -1,3 +1,4 @@
+mod cbz_storage;
 mod commands;
 mod config;
 mod download_manager;
@@ -7,15 +8,24 @@ mod export;
 mod extensions;
 mod hitomi;
 mod hitomi_client;
+mod image_codec;
+mod library;
 mod logger;
+mod sidecar;
 mod types;
 mod utils;
 
 use anyhow::Context;
 use config::Config;
 use download_manager::DownloadManager;
-use events::{DownloadSpeedEvent, DownloadTaskEvent, ExportCbzEvent, ExportPdfEvent, LogEvent};
+use events::{
+    DownloadSpeedEvent, DownloadTaskEvent, ExportBatchEvent, ExportCbzEvent, ExportEpubEvent,
+    ExportPdfEvent, LibraryChangedEvent, LogEvent, RefreshMetadataEvent, SyncExportsEvent,
+};
+use export::ExportCancellations;
+use extensions::AnyhowErrorToStringChain;
 use hitomi_client::HitomiClient;
+use library::LibraryWatcher;
 use parking_lot::RwLock;
 use tauri::{Manager, Wry};
 
@@ -42,11 +52,30 @@ pub fn run() {
             get_downloaded_comics,
             export_pdf,
             export_cbz,
+            export_epub,
+            export_comics,
+            get_export_status_report,
+            cancel_export,
+            get_device_profile_presets,
+            sync_exports,
             get_search_suggestions,
             get_logs_dir_size,
             show_path_in_file_manager,
             get_cover_data,
             get_synced_comic,
+            delete_downloaded_comics,
+            get_trashed_comics,
+            restore_trashed_comics,
+            delete_trashed_comics,
+            get_library_stats,
+            audit_library,
+            find_duplicate_comics,
+            collect_page_store_garbage,
+            verify_library,
+            verify_exported_cbzs,
+            import_comics,
+            refresh_comics_metadata,
+            write_sidecars,
         ])
         .events(tauri_specta::collect_events![
             LogEvent,
@@ -54,6 +83,11 @@ pub fn run() {
             DownloadSpeedEvent,
             ExportPdfEvent,
             ExportCbzEvent,
+            ExportEpubEvent,
+            ExportBatchEvent,
+            RefreshMetadataEvent,
+            LibraryChangedEvent,
+            SyncExportsEvent,
         ]);
 
     #[cfg(debug_assertions)]
@@ -92,11 +126,24 @@ pub fn run() {
             let hitomi_client = HitomiClient::new(app.handle().clone());
             app.manage(hitomi_client);
 
+            let export_cancellations = ExportCancellations::default();
+            app.manage(export_cancellations);
+
             let download_manager = DownloadManager::new(app.handle());
             app.manage(download_manager);
 
+            let library_watcher = LibraryWatcher::new(app.handle());
+            app.manage(library_watcher);
+
             logger::init(app.handle())?;
 
+            // The app is still usable without the watcher, so don't fail the setup because of it
+            if let Err(err) = app.state::<LibraryWatcher>().restart() {
+                let err_title = "Failed to watch the download directory";
+                let string_chain = err.to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+            }
+
             Ok(())
         })
         .run(generate_context())
@@ -0,0 +1,318 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use walkdir::WalkDir;
+
+use crate::{
+    cbz_storage,
+    extensions::{AnyhowErrorToStringChain, PathIsImg},
+    image_codec,
+    types::{Comic, DownloadFormat, SidecarFormat},
+};
+
+use super::{ComicStorage, MANIFEST_FILENAME};
+
+/// Enough bytes to recognize the format of any supported image
+const PAGE_HEADER_LEN: u64 = 64;
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditReport {
+    pub audited_count: u32,
+    /// Only comics with at least one issue are included
+    pub comic_audits: Vec<ComicAudit>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicAudit {
+    pub id: i32,
+    pub title: String,
+    pub metadata_path: PathBuf,
+    /// Set if `metadata.json` can't be converted to `Comic`, other fields are meaningless in this case
+    pub metadata_error: Option<String>,
+    pub expected_page_count: u32,
+    pub page_count: u32,
+    /// 1-based page numbers that have no image file
+    pub missing_pages: Vec<u32>,
+    /// Filenames of pages that are corrupted or whose content doesn't match their extension
+    pub broken_pages: Vec<String>,
+    /// Filenames of pages whose extension doesn't match the recorded download format
+    pub mismatched_format_pages: Vec<String>,
+    /// Filenames of files that don't belong to the comic
+    pub stray_files: Vec<String>,
+    pub repair_actions: Vec<RepairAction>,
+    /// Set if the files of the comic can't be read at all, the page fields are empty in this case
+    pub error: Option<String>,
+}
+
+impl ComicAudit {
+    fn has_issues(&self) -> bool {
+        !self.repair_actions.is_empty() || self.error.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum RepairAction {
+    /// Download the missing or broken pages again
+    RedownloadPages,
+    /// Scan the download directory again to bring `metadata.json` and the files back in line
+    Rescan,
+    /// Nothing usable is left in the download directory, remove it from the library
+    DropEntry,
+}
+
+/// Check every comic in `download_dir` against its `metadata.json`
+///
+/// If `decode_images` is true, every page is fully decoded, which is much slower.
+/// Otherwise only the size and the header of each page are read.
+/// Pages in formats that can't be decoded in this build are only checked by their header
+pub fn audit_library(download_dir: &Path, decode_images: bool) -> AuditReport {
+    let metadata_paths: Vec<PathBuf> = WalkDir::new(download_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| Comic::is_metadata_path(entry.path()))
+        .map(walkdir::DirEntry::into_path)
+        .collect();
+
+    let mut report = AuditReport::default();
+    for metadata_path in metadata_paths {
+        report.audited_count += 1;
+
+        let comic_audit = match Comic::from_metadata(&metadata_path) {
+            Ok(comic) => audit_comic(&comic, &metadata_path, decode_images),
+            Err(err) => ComicAudit {
+                metadata_path,
+                metadata_error: Some(err.to_string_chain()),
+                repair_actions: vec![RepairAction::DropEntry],
+                ..Default::default()
+            },
+        };
+
+        if comic_audit.has_issues() {
+            report.comic_audits.push(comic_audit);
+        }
+    }
+
+    report
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn audit_comic(comic: &Comic, metadata_path: &Path, decode_images: bool) -> ComicAudit {
+    let mut comic_audit = ComicAudit {
+        id: comic.id,
+        title: comic.title.clone(),
+        metadata_path: metadata_path.to_path_buf(),
+        expected_page_count: comic.files.len() as u32,
+        ..Default::default()
+    };
+
+    let Some(comic_download_dir) = comic.comic_download_dir.as_ref() else {
+        return comic_audit;
+    };
+    // The images were deleted after auto export on purpose, there are no pages to check
+    if comic.images_deleted == Some(true) {
+        return comic_audit;
+    }
+
+    // The comic may be stored as a folder or as a CBZ archive
+    let comic_files = ComicStorage::open(comic_download_dir).and_then(|comic_storage| {
+        let file_names = comic_storage.file_names()?;
+        Ok((comic_storage, file_names))
+    });
+    let (mut comic_storage, file_names) = match comic_files {
+        Ok(comic_files) => comic_files,
+        Err(err) => {
+            let err_title = format!("Failed to read the files of `{}`", comic.title);
+            let string_chain = err.to_string_chain();
+            tracing::error!(err_title, message = string_chain);
+            comic_audit.error = Some(string_chain);
+            return comic_audit;
+        }
+    };
+    let paths: Vec<PathBuf> = file_names.iter().map(PathBuf::from).collect();
+
+    let expected_format = comic
+        .download_format
+        .or_else(|| guess_download_format(&paths));
+    let mut existing_pages = HashSet::new();
+
+    for (filename, path) in file_names.into_iter().zip(&paths) {
+        if is_known_non_image_file(&filename) {
+            continue;
+        }
+
+        let page_num = page_num_of(path, comic_audit.expected_page_count);
+        let Some(page_num) = page_num.filter(|_| path.is_img()) else {
+            comic_audit.stray_files.push(filename);
+            continue;
+        };
+        existing_pages.insert(page_num);
+        comic_audit.page_count += 1;
+
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if expected_format.is_some_and(|format| format.to_extension() != extension) {
+            comic_audit.mismatched_format_pages.push(filename.clone());
+        }
+
+        let page_path = comic_storage.display_path(&filename);
+        let check_result = if decode_images {
+            comic_storage
+                .read(&filename)
+                .and_then(|data| check_image(path, &page_path, &data, true))
+        } else {
+            check_page_header(&mut comic_storage, &filename, path, &page_path)
+        };
+        if let Err(err) = check_result {
+            let string_chain = err.to_string_chain();
+            tracing::debug!(
+                id = comic.id,
+                message = string_chain,
+                "Found broken page `{page_path}`"
+            );
+            comic_audit.broken_pages.push(filename);
+        }
+    }
+
+    comic_audit.missing_pages = (1..=comic_audit.expected_page_count)
+        .filter(|page_num| !existing_pages.contains(page_num))
+        .collect();
+
+    comic_audit.stray_files.sort();
+    comic_audit.broken_pages.sort();
+    comic_audit.mismatched_format_pages.sort();
+    comic_audit.repair_actions = suggest_repair_actions(&comic_audit);
+
+    comic_audit
+}
+
+fn suggest_repair_actions(comic_audit: &ComicAudit) -> Vec<RepairAction> {
+    let mut repair_actions = Vec::new();
+
+    if comic_audit.page_count == 0 {
+        // Nothing worth keeping, re-downloading the whole comic is the same as downloading it for the first time
+        repair_actions.push(RepairAction::DropEntry);
+        return repair_actions;
+    }
+
+    if !comic_audit.missing_pages.is_empty()
+        || !comic_audit.broken_pages.is_empty()
+        || !comic_audit.mismatched_format_pages.is_empty()
+    {
+        repair_actions.push(RepairAction::RedownloadPages);
+    }
+
+    if !comic_audit.stray_files.is_empty() {
+        repair_actions.push(RepairAction::Rescan);
+    }
+
+    repair_actions
+}
+
+/// Files that are expected to be in a comic download directory besides the pages
+fn is_known_non_image_file(filename: &str) -> bool {
+    filename == cbz_storage::METADATA_FILENAME
+        || filename == MANIFEST_FILENAME
+        || SidecarFormat::ALL
+            .iter()
+            .any(|format| format.filename() == filename)
+}
+
+/// Get the 1-based page number from a page filename like `0001.webp`,
+/// return `None` if the filename is not a page of a comic with `page_count` pages
+fn page_num_of(path: &Path, page_count: u32) -> Option<u32> {
+    let stem = path.file_stem()?.to_str()?;
+    if stem.len() < 4 || !stem.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let page_num: u32 = stem.parse().ok()?;
+    (1..=page_count).contains(&page_num).then_some(page_num)
+}
+
+/// Use the format of most pages as the download format, for metadata that doesn't record it
+fn guess_download_format(paths: &[PathBuf]) -> Option<DownloadFormat> {
+    let count_of = |format: DownloadFormat| {
+        paths
+            .iter()
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case(format.to_extension()))
+            })
+            .count()
+    };
+
+    let webp_count = count_of(DownloadFormat::Webp);
+    let avif_count = count_of(DownloadFormat::Avif);
+    match (webp_count, avif_count) {
+        (0, 0) => None,
+        (webp_count, avif_count) if webp_count >= avif_count => Some(DownloadFormat::Webp),
+        _ => Some(DownloadFormat::Avif),
+    }
+}
+
+/// Check the size and the header of the page `filename` without reading the whole file
+fn check_page_header(
+    comic_storage: &mut ComicStorage,
+    filename: &str,
+    path: &Path,
+    page_path: &str,
+) -> anyhow::Result<()> {
+    if comic_storage.size(filename)? == 0 {
+        return Err(anyhow::anyhow!("`{page_path}` is empty"));
+    }
+    let header = comic_storage.read_prefix(filename, PAGE_HEADER_LEN)?;
+    check_image(path, page_path, &header, false)
+}
+
+/// Check that `data` matches the extension of `path`, and decode it if `decode_image` is true.
+/// `page_path` is where the page lives, used in error messages
+fn check_image(
+    path: &Path,
+    page_path: &str,
+    data: &[u8],
+    decode_image: bool,
+) -> anyhow::Result<()> {
+    // The `image` crate doesn't know JPEG XL, so only its signature is checked against the extension
+    let is_jxl_extension = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jxl"));
+    if is_jxl_extension || image_codec::is_jxl(data) {
+        if !is_jxl_extension {
+            return Err(anyhow::anyhow!("`{page_path}` is actually a JPEG XL image"));
+        }
+        if !image_codec::is_jxl(data) {
+            return Err(anyhow::anyhow!("`{page_path}` is not a JPEG XL image"));
+        }
+        if decode_image {
+            image_codec::decode(data).context(format!("Failed to decode `{page_path}`"))?;
+        }
+        return Ok(());
+    }
+
+    let format = image::guess_format(data)
+        .context(format!("Failed to guess image format of `{page_path}`"))?;
+    let format_from_extension = ImageFormat::from_path(path).context(format!(
+        "Failed to get image format from extension of `{page_path}`"
+    ))?;
+    if format != format_from_extension {
+        return Err(anyhow::anyhow!(
+            "`{page_path}` is actually a {format:?} image"
+        ));
+    }
+
+    if decode_image && format.reading_enabled() {
+        image::load_from_memory_with_format(data, format)
+            .context(format!("Failed to decode `{page_path}`"))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use specta::Type;
+use tauri::AppHandle;
+
+use crate::{
+    export,
+    extensions::{AnyhowErrorToStringChain, PathIsImg},
+    types::{Comic, ExportFormat},
+};
+
+use super::{get_downloaded_comics, ComicStorage};
+
+/// Name of the checksum manifest, in the format of `sha256sum`, so it can also be checked with `sha256sum -c`
+pub const MANIFEST_FILENAME: &str = "SHA256SUMS";
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    pub verified_count: u32,
+    /// Only comics with at least one issue are included
+    pub comic_verifications: Vec<ComicVerification>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicVerification {
+    pub id: i32,
+    pub title: String,
+    /// The comic download directory, or the exported CBZ file
+    pub path: PathBuf,
+    /// Set if the comic can't be verified at all, such as when it has no manifest, other fields are empty in this case
+    pub error: Option<String>,
+    /// Files whose content doesn't match the manifest
+    pub mismatched_files: Vec<String>,
+    /// Files in the manifest that don't exist
+    pub missing_files: Vec<String>,
+    /// Pages that are not in the manifest
+    pub extra_files: Vec<String>,
+}
+
+impl ComicVerification {
+    fn new(comic: &Comic, path: PathBuf) -> Self {
+        Self {
+            id: comic.id,
+            title: comic.title.clone(),
+            path,
+            ..Default::default()
+        }
+    }
+
+    fn has_issues(&self) -> bool {
+        self.error.is_some()
+            || !self.mismatched_files.is_empty()
+            || !self.missing_files.is_empty()
+            || !self.extra_files.is_empty()
+    }
+}
+
+/// Hash every page in `pages_dir` and write the manifest into it
+pub fn write_manifest(pages_dir: &Path) -> anyhow::Result<()> {
+    let mut comic_storage = ComicStorage::Folder(pages_dir.to_path_buf());
+    let mut hashes = BTreeMap::new();
+    for page_name in comic_storage.page_names()? {
+        let data = comic_storage.read(&page_name)?;
+        hashes.insert(page_name, sha256_hex(&data));
+    }
+
+    let manifest_path = pages_dir.join(MANIFEST_FILENAME);
+    std::fs::write(&manifest_path, format_manifest(&hashes))
+        .context(format!("Failed to write `{}`", manifest_path.display()))?;
+
+    Ok(())
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Format `filename -> hash` as lines of `<hash>  <filename>`
+pub fn format_manifest(hashes: &BTreeMap<String, String>) -> String {
+    hashes
+        .iter()
+        .map(|(filename, hash)| format!("{hash}  {filename}\n"))
+        .collect()
+}
+
+/// Parse lines of `<hash>  <filename>` or `<hash> *<filename>` into `filename -> hash`
+fn parse_manifest(manifest: &str) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut hashes = BTreeMap::new();
+    for line in manifest.lines().filter(|line| !line.trim().is_empty()) {
+        let (hash, filename) = line
+            .split_once(' ')
+            .context(format!("Invalid line in `{MANIFEST_FILENAME}`: `{line}`"))?;
+        let filename = filename
+            .strip_prefix(' ')
+            .or_else(|| filename.strip_prefix('*'))
+            .unwrap_or(filename);
+        hashes.insert(filename.to_string(), hash.to_lowercase());
+    }
+    Ok(hashes)
+}
+
+/// Hash the pages of downloaded comics again and compare them with their manifests
+///
+/// Verify all downloaded comics if `ids` is `None`
+pub fn verify_comics(download_dir: &Path, ids: Option<&[i32]>) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    for comic in get_downloaded_comics(download_dir)
+        .into_iter()
+        .filter(|comic| ids.is_none_or(|ids| ids.contains(&comic.id)))
+    {
+        let Some(comic_download_dir) = comic.comic_download_dir.clone() else {
+            continue;
+        };
+        // The images were deleted after auto export on purpose, only the exports are left to verify
+        if comic.images_deleted == Some(true) {
+            continue;
+        }
+        report.verified_count += 1;
+
+        let mut comic_verification = ComicVerification::new(&comic, comic_download_dir.clone());
+        let result = ComicStorage::open(&comic_download_dir).and_then(|mut comic_storage| {
+            let hashes = read_manifest(&mut comic_storage)?;
+            compare_with_manifest(&hashes, &mut comic_storage, &mut comic_verification)
+        });
+        if let Err(err) = result {
+            comic_verification.error = Some(err.to_string_chain());
+        }
+        if comic_verification.has_issues() {
+            report.comic_verifications.push(comic_verification);
+        }
+    }
+    report
+}
+
+/// Compare the pages in the exported CBZ files of downloaded comics with the manifest the export wrote into the archive.
+/// Exports made before that manifest was written are compared with the manifest in the library,
+/// unless a device profile or the spread handling changed their pages
+///
+/// Verify the exports of all downloaded comics if `ids` is `None`, comics that have not been exported are skipped
+pub fn verify_exported_cbzs(
+    app: &AppHandle,
+    download_dir: &Path,
+    ids: Option<&[i32]>,
+) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    for comic in get_downloaded_comics(download_dir)
+        .into_iter()
+        .filter(|comic| ids.is_none_or(|ids| ids.contains(&comic.id)))
+    {
+        let Some(comic_download_dir) = comic.comic_download_dir.clone() else {
+            continue;
+        };
+        // Volumes of a split export only hold part of the pages each, so only unsplit exports are verified
+        let cbz_path = match export::get_export_path(app, &comic, ExportFormat::Cbz) {
+            Ok(cbz_path) => Some(cbz_path),
+            Err(err) => {
+                let err_title = format!("Failed to get export path of `{}`, skipped", comic.title);
+                let string_chain = err.to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+                continue;
+            }
+        };
+        let Some(cbz_path) = cbz_path.filter(|cbz_path| cbz_path.is_file()) else {
+            continue;
+        };
+        report.verified_count += 1;
+
+        let mut comic_verification = ComicVerification::new(&comic, cbz_path.clone());
+        let result = ComicStorage::open(&cbz_path).and_then(|mut exported_storage| {
+            let has_export_manifest = exported_storage
+                .file_names()?
+                .iter()
+                .any(|name| name == MANIFEST_FILENAME);
+            let hashes = if has_export_manifest {
+                read_manifest(&mut exported_storage)?
+            } else {
+                let mut comic_storage = ComicStorage::open(&comic_download_dir)?;
+                let hashes = read_manifest(&mut comic_storage)?;
+                // Renamed or split pages would all show up as missing and extra
+                let changes_pages = exported_storage
+                    .page_names()?
+                    .iter()
+                    .any(|page_name| !hashes.contains_key(page_name));
+                if changes_pages {
+                    return Err(anyhow!(
+                        "The export changed the pages, so it can't be compared with the library, export it again to verify it"
+                    ));
+                }
+                hashes
+            };
+            compare_with_manifest(&hashes, &mut exported_storage, &mut comic_verification)
+        });
+        if let Err(err) = result {
+            comic_verification.error = Some(err.to_string_chain());
+        }
+        if comic_verification.has_issues() {
+            report.comic_verifications.push(comic_verification);
+        }
+    }
+    report
+}
+
+fn read_manifest(comic_storage: &mut ComicStorage) -> anyhow::Result<BTreeMap<String, String>> {
+    if !comic_storage
+        .file_names()?
+        .iter()
+        .any(|name| name == MANIFEST_FILENAME)
+    {
+        return Err(anyhow!(
+            "`{MANIFEST_FILENAME}` not found, the comic was downloaded before manifests were written"
+        ));
+    }
+    let data = comic_storage.read(MANIFEST_FILENAME)?;
+    let manifest = String::from_utf8_lossy(&data);
+    parse_manifest(&manifest)
+}
+
+fn compare_with_manifest(
+    hashes: &BTreeMap<String, String>,
+    comic_storage: &mut ComicStorage,
+    comic_verification: &mut ComicVerification,
+) -> anyhow::Result<()> {
+    let file_names: HashSet<String> = comic_storage.file_names()?.into_iter().collect();
+
+    for (filename, expected_hash) in hashes {
+        if !file_names.contains(filename) {
+            comic_verification.missing_files.push(filename.clone());
+            continue;
+        }
+        let data = comic_storage.read(filename)?;
+        if sha256_hex(&data) != *expected_hash {
+            comic_verification.mismatched_files.push(filename.clone());
+        }
+    }
+
+    // Metadata files are rewritten on purpose, so only pages can be extra
+    comic_verification.extra_files = file_names
+        .into_iter()
+        .filter(|name| !hashes.contains_key(name) && Path::new(name).is_img())
+        .collect();
+    comic_verification.extra_files.sort();
+
+    Ok(())
+}
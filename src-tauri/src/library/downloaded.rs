@@ -0,0 +1,135 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use indexmap::IndexMap;
+use walkdir::WalkDir;
+
+use crate::{extensions::AnyhowErrorToStringChain, types::Comic};
+
+/// Get all comics in `download_dir`, with the most recently downloaded at the front
+///
+/// Comics that fail to load are logged and skipped.
+/// If a comic has multiple versions in `download_dir`, only the first one is kept
+pub fn get_downloaded_comics(download_dir: &Path) -> Vec<Comic> {
+    let downloaded_comics = get_all_downloaded_comics(download_dir);
+
+    // Group comics by their ID to facilitate deduplication
+    let mut comics_by_id: IndexMap<i32, Vec<Comic>> = IndexMap::new();
+    for comic in downloaded_comics {
+        comics_by_id.entry(comic.id).or_default().push(comic);
+    }
+
+    let mut unique_comics = Vec::new();
+    for (_comic_id, mut comics) in comics_by_id {
+        // The download directories for all comics with the same ID, which may have multiple versions, so we need to deduplicate
+        let comic_download_dirs: Vec<&PathBuf> = comics
+            .iter()
+            .filter_map(|comic| comic.comic_download_dir.as_ref())
+            .collect();
+
+        if comic_download_dirs.is_empty() {
+            // This situation should not actually happen, because the comic metadata file should always have a download directory
+            continue;
+        }
+
+        // Choose the first one as the retained comic
+        let chosen_download_dir = comic_download_dirs[0];
+
+        if comics.len() > 1 {
+            let dir_paths_string = comic_download_dirs
+                .iter()
+                .map(|path| format!("`{}`", path.display()))
+                .collect::<Vec<String>>()
+                .join(", ");
+            // If there are duplicate comics, report an error
+            let comic_title = &comics[0].title;
+            let err_title = "An error occurred while getting downloaded comics";
+            let string_chain = anyhow!("All version paths: [{dir_paths_string}]")
+                .context(format!(
+                    "To proceed, temporarily selected only the version '{}' from the multiple versions found",
+                    chosen_download_dir.display()
+                ))
+                .context(format!(
+                    "Comic `{comic_title}` has multiple versions in the download directory. Please handle this manually, keeping only one",
+                ))
+                .to_string_chain();
+            tracing::error!(err_title, message = string_chain);
+        }
+        // Choose the first one as the retained comic
+        let chosen_comic = comics.remove(0);
+        unique_comics.push(chosen_comic);
+    }
+
+    unique_comics
+}
+
+/// Get all comics in `download_dir`, including every version of comics that are downloaded more than once,
+/// with the most recently downloaded at the front
+///
+/// Comics that fail to load are logged and skipped
+pub fn get_all_downloaded_comics(download_dir: &Path) -> Vec<Comic> {
+    // Traverse the download directory to get the path and modification time of all metadata files
+    let mut metadata_path_with_modify_time = Vec::new();
+    for entry in WalkDir::new(download_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if !Comic::is_metadata_path(path) {
+            continue;
+        }
+        // now the entry is the metadata.json file or a CBZ archive containing it
+        let metadata = match path
+            .metadata()
+            .map_err(anyhow::Error::from)
+            .context(format!(
+                "Failed to get file metadata of `{}`",
+                path.display()
+            )) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                let err_title = "An error occurred while getting downloaded comics, skipped";
+                let string_chain = err.to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+                continue;
+            }
+        };
+
+        let modify_time = match metadata
+            .modified()
+            .map_err(anyhow::Error::from)
+            .context(format!(
+                "Failed to get file modification time of `{}`",
+                path.display()
+            )) {
+            Ok(modify_time) => modify_time,
+            Err(err) => {
+                let err_title = "An error occurred while getting downloaded comics, skipped";
+                let string_chain = err.to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+                continue;
+            }
+        };
+
+        metadata_path_with_modify_time.push((path.to_path_buf(), modify_time));
+    }
+    // Sort by file modification time, with the newest at the front
+    metadata_path_with_modify_time.sort_by(|(_, a), (_, b)| b.cmp(a));
+    // Create Comic from metadata file
+    let mut downloaded_comics = Vec::new();
+    for (metadata_path, _) in metadata_path_with_modify_time {
+        match Comic::from_metadata(&metadata_path).context(format!(
+            "Failed to create Comic from metadata `{}`",
+            metadata_path.display()
+        )) {
+            Ok(comic) => downloaded_comics.push(comic),
+            Err(err) => {
+                let err_title = "An error occurred while getting downloaded comics, skipped";
+                let string_chain = err.to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+            }
+        }
+    }
+
+    downloaded_comics
+}
@@ -0,0 +1,391 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{anyhow, Context};
+use image::imageops::FilterType;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{AppHandle, Manager};
+
+use crate::{config::Config, extensions::AnyhowErrorToStringChain, image_codec, types::Comic};
+
+use super::{dir_size, get_downloaded_comics, ComicStorage};
+
+/// Two pages are considered the same if their hashes differ in at most this many bits
+const MAX_HAMMING_DISTANCE: u32 = 10;
+/// Hashes are split into this many bands of 5 or 6 bits to find the comics worth comparing page by page.
+/// Two hashes within `MAX_HAMMING_DISTANCE` differ in at most that many bands, so they agree exactly on at least one
+const HASH_BAND_COUNT: u32 = MAX_HAMMING_DISTANCE + 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub comics: Vec<DuplicateComic>,
+    /// Similarity of every pair of comics in the group that reached `min_similarity`
+    pub similarities: Vec<ComicSimilarity>,
+    /// The copy with the most pages, then the largest size, then the smallest ID
+    pub suggested_keep_id: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateComic {
+    pub id: i32,
+    pub title: String,
+    pub language: String,
+    pub comic_download_dir: PathBuf,
+    pub page_count: u32,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicSimilarity {
+    pub id: i32,
+    pub other_id: i32,
+    /// Share of the pages of both comics that have a match in the other one, from 0 to 1
+    pub similarity: f64,
+}
+
+/// Perceptual hashes of the pages of a comic, stored in `perceptual_hash_cache_dir`
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageHashCache {
+    /// Page filename -> hash
+    pages: BTreeMap<String, CachedPageHash>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedPageHash {
+    size: u64,
+    modified_millis: u64,
+    /// `None` if the page can't be decoded in this build, so it is not tried again
+    hash: Option<u64>,
+}
+
+struct HashedComic {
+    duplicate_comic: DuplicateComic,
+    hashes: Vec<u64>,
+}
+
+pub fn perceptual_hash_cache_dir(app: &AppHandle) -> anyhow::Result<PathBuf> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .context("failed to get app_data_dir")?;
+    Ok(app_data_dir.join("perceptual-hashes"))
+}
+
+/// Group downloaded comics whose pages look the same, such as other languages or re-uploads of the same work
+///
+/// `min_similarity` is greater than 0 and at most 1, only comics at least this similar are grouped
+pub fn find_duplicate_comics(
+    app: &AppHandle,
+    min_similarity: f64,
+) -> anyhow::Result<Vec<DuplicateGroup>> {
+    if min_similarity.is_nan() || min_similarity <= 0.0 || min_similarity > 1.0 {
+        return Err(anyhow!(
+            "`min_similarity` must be greater than 0 and at most 1, got `{min_similarity}`"
+        ));
+    }
+    let download_dir = app.state::<RwLock<Config>>().read().download_dir.clone();
+    let cache_dir = perceptual_hash_cache_dir(app)?;
+    std::fs::create_dir_all(&cache_dir).context(format!(
+        "Failed to create directory `{}`",
+        cache_dir.display()
+    ))?;
+
+    let mut hashed_comics = Vec::new();
+    for comic in get_downloaded_comics(&download_dir) {
+        match hash_comic(&comic, &cache_dir) {
+            Ok(hashed_comic) => hashed_comics.push(hashed_comic),
+            Err(err) => {
+                let err_title = format!("Failed to hash pages of `{}`, skipped", comic.title);
+                let string_chain = err.to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+            }
+        }
+    }
+
+    let mut similarities = Vec::new();
+    for (i, j) in candidate_pairs(&hashed_comics, min_similarity) {
+        let similarity = similarity_between(
+            &hashed_comics[i].hashes,
+            &hashed_comics[j].hashes,
+            min_similarity,
+        );
+        if similarity >= min_similarity {
+            similarities.push((i, j, similarity));
+        }
+    }
+
+    // Every comic starts in a group of its own, similar pairs merge their groups
+    let mut group_of: Vec<usize> = (0..hashed_comics.len()).collect();
+    for &(i, j, _) in &similarities {
+        let (group_i, group_j) = (find_group(&mut group_of, i), find_group(&mut group_of, j));
+        group_of[group_j] = group_i;
+    }
+
+    let mut members_by_group: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..hashed_comics.len() {
+        let group = find_group(&mut group_of, i);
+        members_by_group.entry(group).or_default().push(i);
+    }
+    let mut similarities_by_group: HashMap<usize, Vec<ComicSimilarity>> = HashMap::new();
+    for (i, j, similarity) in similarities {
+        let group = find_group(&mut group_of, i);
+        similarities_by_group
+            .entry(group)
+            .or_default()
+            .push(ComicSimilarity {
+                id: hashed_comics[i].duplicate_comic.id,
+                other_id: hashed_comics[j].duplicate_comic.id,
+                similarity,
+            });
+    }
+
+    let duplicate_groups = members_by_group
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .filter_map(|(group, members)| {
+            let mut comics: Vec<DuplicateComic> = members
+                .into_iter()
+                .map(|i| hashed_comics[i].duplicate_comic.clone())
+                .collect();
+            comics.sort_by_key(|comic| (Reverse(comic.page_count), Reverse(comic.size), comic.id));
+            let suggested_keep_id = comics.first()?.id;
+            let similarities = similarities_by_group.remove(&group).unwrap_or_default();
+            Some(DuplicateGroup {
+                comics,
+                similarities,
+                suggested_keep_id,
+            })
+        })
+        .collect();
+
+    Ok(duplicate_groups)
+}
+
+fn find_group(group_of: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while group_of[root] != root {
+        root = group_of[root];
+    }
+    // Point everything on the way directly to the root, so later lookups are short
+    let mut current = i;
+    while group_of[current] != root {
+        let next = group_of[current];
+        group_of[current] = root;
+        current = next;
+    }
+    root
+}
+
+/// Hash the pages of `comic`, reusing the cached hashes of pages that haven't changed
+fn hash_comic(comic: &Comic, cache_dir: &Path) -> anyhow::Result<HashedComic> {
+    let comic_title = &comic.title;
+    let comic_download_dir = comic
+        .comic_download_dir
+        .as_ref()
+        .context("`comic_download_dir` field is `None`")?;
+
+    // The comic may be stored as a folder or as a CBZ archive
+    let mut comic_storage = ComicStorage::open(comic_download_dir)?;
+    let page_names = comic_storage.page_names()?;
+
+    let cache_path = cache_dir.join(format!("{}.json", comic.id));
+    let old_cache: PageHashCache = std::fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|cache_string| serde_json::from_str(&cache_string).ok())
+        .unwrap_or_default();
+
+    let mut cache = PageHashCache::default();
+    for filename in &page_names {
+        // The pages in an archive can only change along with the archive itself
+        let stamp_path = match &comic_storage {
+            ComicStorage::Folder(dir) => dir.join(filename),
+            ComicStorage::Cbz { cbz_path, .. } => cbz_path.clone(),
+        };
+        let metadata = std::fs::metadata(&stamp_path).context(format!(
+            "Failed to get metadata of `{}`",
+            stamp_path.display()
+        ))?;
+        let size = metadata.len();
+        #[allow(clippy::cast_possible_truncation)]
+        let modified_millis = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_millis() as u64);
+
+        let cached_page_hash = match old_cache.pages.get(filename) {
+            Some(cached) if cached.size == size && cached.modified_millis == modified_millis => {
+                cached.clone()
+            }
+            _ => {
+                let page_path = comic_storage.display_path(filename);
+                let hash_result = comic_storage
+                    .read(filename)
+                    .and_then(|data| difference_hash(&data, &page_path));
+                let hash = match hash_result {
+                    Ok(hash) => Some(hash),
+                    Err(err) => {
+                        let string_chain = err.to_string_chain();
+                        tracing::debug!(
+                            id = comic.id,
+                            message = string_chain,
+                            "Failed to hash page `{page_path}`"
+                        );
+                        None
+                    }
+                };
+                CachedPageHash {
+                    size,
+                    modified_millis,
+                    hash,
+                }
+            }
+        };
+        cache.pages.insert(filename.clone(), cached_page_hash);
+    }
+
+    let cache_string = serde_json::to_string(&cache).context("Failed to serialize page hashes")?;
+    std::fs::write(&cache_path, cache_string).context(format!(
+        "Failed to save page hashes of `{comic_title}` to `{}`",
+        cache_path.display()
+    ))?;
+
+    // Blank pages all hash to 0 and would make unrelated comics look alike
+    let hashes: Vec<u64> = cache
+        .pages
+        .values()
+        .filter_map(|cached| cached.hash)
+        .filter(|hash| *hash != 0)
+        .collect();
+
+    #[allow(clippy::cast_possible_truncation)]
+    let duplicate_comic = DuplicateComic {
+        id: comic.id,
+        title: comic.title.clone(),
+        language: comic.language.clone(),
+        comic_download_dir: comic_download_dir.clone(),
+        page_count: page_names.len() as u32,
+        size: dir_size(comic_download_dir),
+    };
+
+    Ok(HashedComic {
+        duplicate_comic,
+        hashes,
+    })
+}
+
+/// 64-bit difference hash, each bit tells whether a pixel is brighter than its right neighbour
+/// in a 9x8 grayscale thumbnail. It survives rescaling, recompression and format changes
+fn difference_hash(data: &[u8], page_path: &str) -> anyhow::Result<u64> {
+    let img = image_codec::decode(data).context(format!("Failed to decode `{page_path}`"))?;
+    let thumbnail = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = thumbnail.get_pixel(x, y)[0];
+            let right = thumbnail.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+
+    Ok(hash)
+}
+
+/// The pairs `(i, j)` with `i < j` of `hashed_comics` that may reach `min_similarity`
+///
+/// Comparing every page of every pair doesn't scale to large libraries, so the pages are bucketed by the bands of their hashes first.
+/// Pages close enough to match always agree exactly on one band, so a pair is only a candidate
+/// if enough of its pages share a band with a page of the other comic to reach `min_similarity`
+#[allow(clippy::cast_precision_loss)]
+fn candidate_pairs(hashed_comics: &[HashedComic], min_similarity: f64) -> Vec<(usize, usize)> {
+    let mut comics_by_band: HashMap<(u32, u64), Vec<usize>> = HashMap::new();
+    for (i, hashed_comic) in hashed_comics.iter().enumerate() {
+        let bands: HashSet<(u32, u64)> = hashed_comic
+            .hashes
+            .iter()
+            .flat_map(|hash| hash_bands(*hash))
+            .collect();
+        for band in bands {
+            comics_by_band.entry(band).or_default().push(i);
+        }
+    }
+
+    // (i, j) -> number of pages of comic `i` that share a band with a page of comic `j`
+    let mut shared_page_counts: HashMap<(usize, usize), usize> = HashMap::new();
+    for (i, hashed_comic) in hashed_comics.iter().enumerate() {
+        for hash in &hashed_comic.hashes {
+            let other_comics: HashSet<usize> = hash_bands(*hash)
+                .filter_map(|band| comics_by_band.get(&band))
+                .flatten()
+                .copied()
+                .filter(|j| *j != i)
+                .collect();
+            for j in other_comics {
+                *shared_page_counts.entry((i, j)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut pairs: Vec<(usize, usize)> = shared_page_counts
+        .iter()
+        .filter(|((i, j), _)| i < j)
+        .filter(|((i, j), count)| {
+            let other_count = shared_page_counts.get(&(*j, *i)).copied().unwrap_or(0);
+            let total = hashed_comics[*i].hashes.len() + hashed_comics[*j].hashes.len();
+            (**count + other_count) as f64 / total as f64 >= min_similarity
+        })
+        .map(|(pair, _)| *pair)
+        .collect();
+    pairs.sort_unstable();
+    pairs
+}
+
+/// The bands of `hash` tagged with their position, so that equal values in different positions don't collide
+fn hash_bands(hash: u64) -> impl Iterator<Item = (u32, u64)> {
+    (0..HASH_BAND_COUNT).map(move |position| {
+        let start = position * u64::BITS / HASH_BAND_COUNT;
+        let end = (position + 1) * u64::BITS / HASH_BAND_COUNT;
+        let band = (hash >> start) & ((1 << (end - start)) - 1);
+        (position, band)
+    })
+}
+
+/// Share of the pages of both comics that have a close enough match in the other one,
+/// returns 0 without comparing if the page counts are too different to reach `min_similarity`
+#[allow(clippy::cast_precision_loss)]
+fn similarity_between(hashes: &[u64], other_hashes: &[u64], min_similarity: f64) -> f64 {
+    let total = hashes.len() + other_hashes.len();
+    if total == 0 {
+        return 0.0;
+    }
+    let max_similarity = (2 * hashes.len().min(other_hashes.len())) as f64 / total as f64;
+    if max_similarity < min_similarity {
+        return 0.0;
+    }
+
+    let count_matched = |hashes: &[u64], other_hashes: &[u64]| {
+        hashes
+            .iter()
+            .filter(|hash| {
+                other_hashes
+                    .iter()
+                    .any(|other| (*hash ^ other).count_ones() <= MAX_HAMMING_DISTANCE)
+            })
+            .count()
+    };
+    let matched = count_matched(hashes, other_hashes) + count_matched(other_hashes, hashes);
+
+    matched as f64 / total as f64
+}
@@ -0,0 +1,485 @@
+use std::{
+    cmp::Ordering,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use parking_lot::RwLock;
+use regex_lite::Regex;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{AppHandle, Manager};
+use walkdir::WalkDir;
+
+use crate::{
+    cbz_storage::{self, open_zip_archive},
+    config::Config,
+    extensions::{AnyhowErrorToStringChain, PathIsImg},
+    hitomi, sidecar,
+    types::{Comic, DownloadFormat, SidecarFormat, StorageMode},
+};
+
+use super::{store_page, write_manifest};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum ImportMode {
+    /// Move the pages into the library, the source pages and `.cbz` files are deleted once the comic is stored
+    Move,
+    /// Copy the pages into the library, leaving the source untouched
+    Copy,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub imported: Vec<ImportedComic>,
+    pub skipped: Vec<SkippedImport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedComic {
+    pub source_path: PathBuf,
+    pub comic: Comic,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedImport {
+    pub source_path: PathBuf,
+    pub reason: String,
+}
+
+/// A gallery found in the directory being imported
+enum ImportSource {
+    /// A folder that contains the pages directly
+    Dir(PathBuf),
+    /// A `.cbz` archive
+    Cbz(PathBuf),
+}
+
+impl ImportSource {
+    fn path(&self) -> &Path {
+        match self {
+            ImportSource::Dir(path) | ImportSource::Cbz(path) => path,
+        }
+    }
+
+    /// Detect the gallery ID from the `Web` field of `ComicInfo.xml`, then from the folder name or filename
+    fn detect_id(&self) -> anyhow::Result<i32> {
+        let comic_info_xml = match self.read_comic_info_xml() {
+            Ok(comic_info_xml) => comic_info_xml,
+            Err(err) => {
+                let string_chain = err.to_string_chain();
+                tracing::debug!(message = string_chain, "No usable `ComicInfo.xml`");
+                None
+            }
+        };
+        if let Some(id) = comic_info_xml.as_deref().and_then(id_from_comic_info_xml) {
+            return Ok(id);
+        }
+
+        let name = self
+            .path()
+            .file_stem()
+            .context(format!("Failed to get name of `{}`", self.path().display()))?
+            .to_string_lossy();
+        id_from_name(&name).context(format!(
+            "Failed to detect gallery ID from `ComicInfo.xml` or name `{name}`"
+        ))
+    }
+
+    fn read_comic_info_xml(&self) -> anyhow::Result<Option<String>> {
+        match self {
+            ImportSource::Dir(dir) => {
+                let comic_info_path = dir.join("ComicInfo.xml");
+                if !comic_info_path.is_file() {
+                    return Ok(None);
+                }
+                let comic_info_xml = std::fs::read_to_string(&comic_info_path)
+                    .context(format!("Failed to read `{}`", comic_info_path.display()))?;
+                Ok(Some(comic_info_xml))
+            }
+            ImportSource::Cbz(cbz_path) => {
+                let mut zip_archive = open_zip_archive(cbz_path)?;
+                let Ok(mut zip_file) = zip_archive.by_name("ComicInfo.xml") else {
+                    return Ok(None);
+                };
+                let mut comic_info_xml = String::new();
+                zip_file
+                    .read_to_string(&mut comic_info_xml)
+                    .context(format!(
+                        "Failed to read `ComicInfo.xml` in `{}`",
+                        cbz_path.display()
+                    ))?;
+                Ok(Some(comic_info_xml))
+            }
+        }
+    }
+
+    /// Get the page names in reading order
+    fn page_names(&self) -> anyhow::Result<Vec<String>> {
+        let mut page_names: Vec<String> = match self {
+            ImportSource::Dir(dir) => std::fs::read_dir(dir)
+                .context(format!("Failed to read directory `{}`", dir.display()))?
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file() && path.is_img())
+                .filter_map(|path| Some(path.file_name()?.to_string_lossy().to_string()))
+                .collect(),
+            ImportSource::Cbz(cbz_path) => open_zip_archive(cbz_path)?
+                .file_names()
+                .filter(|name| Path::new(name).is_img())
+                .map(ToString::to_string)
+                .collect(),
+        };
+        page_names.sort_by(|a, b| natural_cmp(a, b));
+        Ok(page_names)
+    }
+
+    /// Read the content of the page `page_name`
+    fn read_page(&self, page_name: &str) -> anyhow::Result<Vec<u8>> {
+        match self {
+            ImportSource::Dir(dir) => {
+                let page_path = dir.join(page_name);
+                std::fs::read(&page_path)
+                    .context(format!("Failed to read `{}`", page_path.display()))
+            }
+            ImportSource::Cbz(cbz_path) => {
+                let mut zip_archive = open_zip_archive(cbz_path)?;
+                let mut zip_file = zip_archive.by_name(page_name).context(format!(
+                    "Failed to find `{page_name}` in `{}`",
+                    cbz_path.display()
+                ))?;
+                let mut data = Vec::new();
+                zip_file.read_to_end(&mut data).context(format!(
+                    "Failed to read `{page_name}` in `{}`",
+                    cbz_path.display()
+                ))?;
+                Ok(data)
+            }
+        }
+    }
+
+    /// Clean up the source after the comic has been stored in the library,
+    /// the pages are always copied first so a failed import leaves the source untouched
+    fn finish(&self, mode: ImportMode, page_names: &[String]) -> anyhow::Result<()> {
+        if mode == ImportMode::Copy {
+            return Ok(());
+        }
+        match self {
+            ImportSource::Dir(dir) => {
+                for page_name in page_names {
+                    let page_path = dir.join(page_name);
+                    std::fs::remove_file(&page_path)
+                        .context(format!("Failed to delete `{}`", page_path.display()))?;
+                }
+                // The folder may still contain files that are not pages, only delete it if it's empty
+                let _ = std::fs::remove_dir(dir);
+            }
+            ImportSource::Cbz(cbz_path) => {
+                std::fs::remove_file(cbz_path)
+                    .context(format!("Failed to delete `{}`", cbz_path.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Import the galleries in `source_dir` into the library
+///
+/// Every folder that contains images and every `.cbz` file in `source_dir` is treated as a gallery.
+/// The gallery info is fetched from hitomi, so the galleries must still exist on the site.
+/// If the download directory is inside `source_dir`, it's skipped, so the library is never imported into itself
+pub async fn import_comics(
+    app: &AppHandle,
+    source_dir: &Path,
+    mode: ImportMode,
+) -> anyhow::Result<ImportReport> {
+    let download_dir = app.state::<RwLock<Config>>().read().download_dir.clone();
+    // Compare the canonical paths, so that symlinks and relative paths can't hide that one is inside the other
+    let source_dir = std::fs::canonicalize(source_dir).unwrap_or_else(|_| source_dir.to_path_buf());
+    let download_dir = std::fs::canonicalize(&download_dir).unwrap_or(download_dir);
+    if source_dir.starts_with(&download_dir) {
+        return Err(anyhow!(
+            "`{}` is already in the download directory",
+            source_dir.display()
+        ));
+    }
+
+    let mut report = ImportReport::default();
+    for source in find_import_sources(&source_dir, &download_dir) {
+        let source_path = source.path().to_path_buf();
+        match import_comic(app, &source, mode).await {
+            Ok(comic) => {
+                tracing::debug!(
+                    id = comic.id,
+                    comic_title = comic.title,
+                    "Imported `{}` successfully",
+                    source_path.display()
+                );
+                report.imported.push(ImportedComic { source_path, comic });
+            }
+            Err(err) => {
+                let err_title = format!("Skipped importing `{}`", source_path.display());
+                let string_chain = err.to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+                report.skipped.push(SkippedImport {
+                    source_path,
+                    reason: string_chain,
+                });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+async fn import_comic(
+    app: &AppHandle,
+    source: &ImportSource,
+    mode: ImportMode,
+) -> anyhow::Result<Comic> {
+    let id = source.detect_id()?;
+    let gallery_info = hitomi::get_gallery_info(id)
+        .await
+        .context(format!("Failed to get gallery info for `{id}`"))?;
+    let mut comic = Comic::from_gallery_info(app, gallery_info)
+        .await
+        .context(format!("Failed to create Comic from gallery_info `{id}`"))?;
+    let comic_title = comic.title.clone();
+
+    if let Some(comic_download_dir) = comic.comic_download_dir.as_ref() {
+        return Err(anyhow!(
+            "`{comic_title}` is already in the library at `{}`",
+            comic_download_dir.display()
+        ));
+    }
+
+    let page_names = source.page_names()?;
+    if page_names.len() != comic.files.len() {
+        // The pages can't be matched to the gallery files reliably
+        return Err(anyhow!(
+            "Found `{}` pages, but gallery `{comic_title}` has `{}` pages",
+            page_names.len(),
+            comic.files.len()
+        ));
+    }
+
+    comic.update_dir_name_fields_by_fmt(app).context(format!(
+        "Failed to update directory name fields by fmt of `{comic_title}`"
+    ))?;
+    let comic_download_dir = comic
+        .comic_download_dir
+        .clone()
+        .context("`comic_download_dir` field is `None`")?;
+    let cbz_path = cbz_storage::cbz_path_of(&comic_download_dir);
+    for path in [&comic_download_dir, &cbz_path] {
+        if path.exists() {
+            return Err(anyhow!("`{}` already exists", path.display()));
+        }
+    }
+    // Stage the pages in the temporary download directory, just like downloading does
+    let temp_download_dir = comic.get_temp_download_dir()?;
+    std::fs::create_dir_all(&temp_download_dir).context(format!(
+        "Failed to create directory `{}`",
+        temp_download_dir.display()
+    ))?;
+
+    let (download_dir, storage_mode, sidecar_formats) = {
+        let config = app.state::<RwLock<Config>>();
+        let config = config.read();
+        (
+            config.download_dir.clone(),
+            config.storage_mode,
+            config.sidecar_formats.clone(),
+        )
+    };
+    let stored_path = match stage_pages(
+        source,
+        &page_names,
+        &temp_download_dir,
+        &download_dir,
+        storage_mode,
+    )
+    .and_then(|extensions| {
+        comic.download_format = [DownloadFormat::Webp, DownloadFormat::Avif]
+            .into_iter()
+            .find(|format| extensions.iter().all(|ext| ext == format.to_extension()));
+        store_pages(
+            &comic,
+            &temp_download_dir,
+            &comic_download_dir,
+            storage_mode,
+            &sidecar_formats,
+        )
+    }) {
+        Ok(stored_path) => stored_path,
+        Err(err) => {
+            // Nothing was taken from the source yet, only the staged copies have to go
+            let _ = std::fs::remove_dir_all(&temp_download_dir);
+            return Err(err);
+        }
+    };
+    comic.comic_download_dir = Some(stored_path);
+    comic.is_downloaded = Some(true);
+
+    source.finish(mode, &page_names)?;
+
+    Ok(comic)
+}
+
+/// Copy the pages of `source` into `temp_download_dir` the way `storage_mode` saves downloaded pages,
+/// return the extension of every page
+fn stage_pages(
+    source: &ImportSource,
+    page_names: &[String],
+    temp_download_dir: &Path,
+    download_dir: &Path,
+    storage_mode: StorageMode,
+) -> anyhow::Result<Vec<String>> {
+    let mut extensions = Vec::new();
+    for (i, page_name) in page_names.iter().enumerate() {
+        let extension = Path::new(page_name)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let save_path = temp_download_dir.join(format!("{:04}.{extension}", i + 1));
+        let data = source.read_page(page_name)?;
+        match storage_mode {
+            StorageMode::Folder | StorageMode::Cbz => std::fs::write(&save_path, &data)
+                .context(format!("Failed to write `{}`", save_path.display()))?,
+            StorageMode::DeduplicatedFolder => store_page(download_dir, &data, &save_path)?,
+        }
+        extensions.push(extension);
+    }
+    Ok(extensions)
+}
+
+/// Move the staged pages to their place in the library with metadata and checksum manifest,
+/// return the folder or, for `StorageMode::Cbz`, the archive the comic is stored at
+fn store_pages(
+    comic: &Comic,
+    temp_download_dir: &Path,
+    comic_download_dir: &Path,
+    storage_mode: StorageMode,
+    sidecar_formats: &[SidecarFormat],
+) -> anyhow::Result<PathBuf> {
+    if storage_mode == StorageMode::Cbz {
+        let cbz_path = cbz_storage::cbz_path_of(comic_download_dir);
+        if let Some(parent) = cbz_path.parent() {
+            std::fs::create_dir_all(parent)
+                .context(format!("Failed to create directory `{}`", parent.display()))?;
+        }
+        cbz_storage::pack(comic, temp_download_dir, &cbz_path, sidecar_formats)?;
+        std::fs::remove_dir_all(temp_download_dir).context(format!(
+            "Failed to delete directory `{}`",
+            temp_download_dir.display()
+        ))?;
+        return Ok(cbz_path);
+    }
+
+    // Written before the rename, so the comic never shows up in the library without its metadata
+    comic.save_metadata(temp_download_dir)?;
+    write_manifest(temp_download_dir)?;
+    // Sidecars are only a convenience for other tools, failing to write them doesn't fail the import
+    if let Err(err) = sidecar::write_sidecars(comic, temp_download_dir, sidecar_formats) {
+        let err_title = format!("Failed to write sidecar metadata of `{}`", comic.title);
+        let string_chain = err.to_string_chain();
+        tracing::error!(err_title, message = string_chain);
+    }
+    std::fs::rename(temp_download_dir, comic_download_dir).context(format!(
+        "Failed to rename `{}` to `{}`",
+        temp_download_dir.display(),
+        comic_download_dir.display()
+    ))?;
+    Ok(comic_download_dir.to_path_buf())
+}
+
+fn find_import_sources(source_dir: &Path, download_dir: &Path) -> Vec<ImportSource> {
+    let mut sources = Vec::new();
+    for entry in WalkDir::new(source_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.path() != download_dir)
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        if entry.file_type().is_file() {
+            let is_cbz = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("cbz"));
+            if is_cbz {
+                sources.push(ImportSource::Cbz(path.to_path_buf()));
+            }
+            continue;
+        }
+
+        let contains_img = std::fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .any(|entry| entry.path().is_img())
+            })
+            .unwrap_or(false);
+        if contains_img {
+            sources.push(ImportSource::Dir(path.to_path_buf()));
+        }
+    }
+    sources
+}
+
+/// Get the gallery ID from a link like `https://hitomi.la/doujinshi/title-123456.html` in the `Web` field
+fn id_from_comic_info_xml(comic_info_xml: &str) -> Option<i32> {
+    let re = Regex::new(r"<Web>[^<]*hitomi\.la/[^<]*?([0-9]+)\.html[^<]*</Web>").ok()?;
+    let caps = re.captures(comic_info_xml)?;
+    caps[1].parse().ok()
+}
+
+/// Use the last run of 5 to 8 digits in `name` as the gallery ID, e.g. `title - 123456` or `[123456] title`
+fn id_from_name(name: &str) -> Option<i32> {
+    let re = Regex::new(r"[0-9]+").ok()?;
+    re.find_iter(name)
+        .map(|m| m.as_str())
+        .filter(|digits| (5..=8).contains(&digits.len()))
+        .last()
+        .and_then(|digits| digits.parse().ok())
+}
+
+/// Compare strings with the digits in them compared as numbers, so that `2.jpg` comes before `10.jpg`
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) if a_char.is_ascii_digit() && b_char.is_ascii_digit() => {
+                let mut a_num = String::new();
+                while let Some(c) = a_chars.next_if(char::is_ascii_digit) {
+                    a_num.push(c);
+                }
+                let mut b_num = String::new();
+                while let Some(c) = b_chars.next_if(char::is_ascii_digit) {
+                    b_num.push(c);
+                }
+                let a_num = a_num.trim_start_matches('0');
+                let b_num = b_num.trim_start_matches('0');
+                let ordering = a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(a_char), Some(b_char)) => {
+                let ordering = a_char.cmp(&b_char);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
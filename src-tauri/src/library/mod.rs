@@ -0,0 +1,23 @@
+mod audit;
+mod checksum;
+mod downloaded;
+mod duplicates;
+mod import;
+mod page_store;
+mod refresh;
+mod stats;
+mod storage;
+mod trash;
+mod watcher;
+
+pub use audit::*;
+pub use checksum::*;
+pub use downloaded::*;
+pub use duplicates::*;
+pub use import::*;
+pub use page_store::*;
+pub use refresh::*;
+pub use stats::*;
+pub use storage::*;
+pub use trash::*;
+pub use watcher::*;
@@ -0,0 +1,207 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use specta::Type;
+use walkdir::WalkDir;
+
+use crate::extensions::AnyhowErrorToStringChain;
+
+/// Directory in `Config::download_dir` that holds the pages of `StorageMode::DeduplicatedFolder`.
+/// It is inside `download_dir` so that hardlinks into the comic folders are on the same file system
+const PAGE_STORE_DIR_NAME: &str = ".page-store";
+/// Unfinished blobs younger than this may still be written by a running download, so they are not collected
+const TEMP_BLOB_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct PageStoreGcReport {
+    /// Number of blobs left in the page store after the collection
+    pub blob_count: u32,
+    pub removed_count: u32,
+    pub reclaimed_size: u64,
+}
+
+pub fn page_store_dir(download_dir: &Path) -> PathBuf {
+    download_dir.join(PAGE_STORE_DIR_NAME)
+}
+
+/// Save `data` to `save_path` as a hardlink to its blob in the page store, creating the blob if needed
+///
+/// Falls back to a plain copy if the file system doesn't support hardlinks
+pub fn store_page(download_dir: &Path, data: &[u8], save_path: &Path) -> anyhow::Result<()> {
+    let hash = format!("{:x}", Sha256::digest(data));
+    let extension = save_path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let blob_dir = page_store_dir(download_dir).join(&hash[..2]);
+    let blob_path = blob_dir.join(format!("{hash}.{extension}"));
+
+    if !blob_path.exists() {
+        std::fs::create_dir_all(&blob_dir).context(format!(
+            "Failed to create directory `{}`",
+            blob_dir.display()
+        ))?;
+        // Write to a temporary file first, so a blob is never seen half written
+        let temp_blob_path = blob_dir.join(format!("{hash}.{}.tmp", uuid::Uuid::new_v4()));
+        std::fs::write(&temp_blob_path, data)
+            .context(format!("Failed to write `{}`", temp_blob_path.display()))?;
+        std::fs::rename(&temp_blob_path, &blob_path).context(format!(
+            "Failed to rename `{}` to `{}`",
+            temp_blob_path.display(),
+            blob_path.display()
+        ))?;
+    }
+
+    if let Err(err) = std::fs::hard_link(&blob_path, save_path).map_err(anyhow::Error::from) {
+        let string_chain = err.to_string_chain();
+        tracing::debug!(
+            message = string_chain,
+            "Failed to hardlink `{}`, saved a copy instead",
+            blob_path.display()
+        );
+        std::fs::write(save_path, data)
+            .context(format!("Failed to write `{}`", save_path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Delete the blobs in the page store that no file in `download_dir` refers to anymore
+///
+/// Every page is a hardlink to its blob, so a blob whose only link is itself is unreferenced.
+/// Pages in unfinished `.downloading-*` directories are links as well, so running downloads are not affected.
+/// Pages saved as copies because hardlinks failed don't need their blob either
+pub fn collect_page_store_garbage(download_dir: &Path) -> anyhow::Result<PageStoreGcReport> {
+    let page_store_dir = page_store_dir(download_dir);
+    let mut report = PageStoreGcReport::default();
+    if !page_store_dir.exists() {
+        return Ok(report);
+    }
+
+    let entries: Vec<walkdir::DirEntry> = WalkDir::new(&page_store_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .collect();
+    let (temp_blobs, blobs): (Vec<walkdir::DirEntry>, Vec<walkdir::DirEntry>) = entries
+        .into_iter()
+        .partition(|entry| entry.path().extension().is_some_and(|ext| ext == "tmp"));
+
+    for blob in blobs {
+        let blob_path = blob.path();
+        if link_count(blob_path)? > 1 {
+            report.blob_count += 1;
+            continue;
+        }
+
+        let size = blob.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        std::fs::remove_file(blob_path)
+            .context(format!("Failed to delete `{}`", blob_path.display()))?;
+        report.removed_count += 1;
+        report.reclaimed_size += size;
+    }
+
+    for temp_blob in temp_blobs {
+        let is_stale = temp_blob
+            .metadata()
+            .ok()
+            .and_then(|metadata| metadata.modified().ok())
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > TEMP_BLOB_GRACE_PERIOD);
+        if !is_stale {
+            continue;
+        }
+
+        let temp_blob_path = temp_blob.path();
+        let size = temp_blob
+            .metadata()
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        std::fs::remove_file(temp_blob_path)
+            .context(format!("Failed to delete `{}`", temp_blob_path.display()))?;
+        report.removed_count += 1;
+        report.reclaimed_size += size;
+    }
+
+    remove_empty_blob_dirs(&page_store_dir);
+
+    Ok(report)
+}
+
+/// Number of hardlinks to the file at `path`, including `path` itself
+#[cfg(unix)]
+fn link_count(path: &Path) -> anyhow::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = std::fs::metadata(path)
+        .context(format!("Failed to get metadata of `{}`", path.display()))?;
+    Ok(metadata.nlink())
+}
+
+/// Number of hardlinks to the file at `path`, including `path` itself
+#[cfg(windows)]
+fn link_count(path: &Path) -> anyhow::Result<u64> {
+    let file_information = file_information(path)?;
+    Ok(u64::from(file_information.nNumberOfLinks))
+}
+
+/// Identify the file at `path` as `(device, inode)`, which is the same for all its hardlinks
+#[cfg(unix)]
+pub fn file_identity(path: &Path) -> anyhow::Result<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = std::fs::metadata(path)
+        .context(format!("Failed to get metadata of `{}`", path.display()))?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+/// Identify the file at `path` as `(volume serial number, file index)`, which is the same for all its hardlinks
+#[cfg(windows)]
+pub fn file_identity(path: &Path) -> anyhow::Result<(u64, u64)> {
+    let file_information = file_information(path)?;
+    let file_index = (u64::from(file_information.nFileIndexHigh) << 32)
+        | u64::from(file_information.nFileIndexLow);
+    Ok((u64::from(file_information.dwVolumeSerialNumber), file_index))
+}
+
+#[cfg(windows)]
+fn file_information(
+    path: &Path,
+) -> anyhow::Result<windows_sys::Win32::Storage::FileSystem::BY_HANDLE_FILE_INFORMATION> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION,
+    };
+
+    let file = std::fs::File::open(path).context(format!("Failed to open `{}`", path.display()))?;
+    // SAFETY: `BY_HANDLE_FILE_INFORMATION` is plain data, all zeros is a valid value
+    let mut file_information: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+    // SAFETY: the handle stays valid while `file` is open, and `file_information` outlives the call
+    let succeeded =
+        unsafe { GetFileInformationByHandle(file.as_raw_handle(), &raw mut file_information) };
+    if succeeded == 0 {
+        return Err(std::io::Error::last_os_error()).context(format!(
+            "Failed to get file information of `{}`",
+            path.display()
+        ));
+    }
+    Ok(file_information)
+}
+
+fn remove_empty_blob_dirs(page_store_dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(page_store_dir) else {
+        return;
+    };
+    for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+        let is_empty = std::fs::read_dir(&path).is_ok_and(|mut entries| entries.next().is_none());
+        if is_empty {
+            let _ = std::fs::remove_dir(&path);
+        }
+    }
+}
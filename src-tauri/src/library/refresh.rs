@@ -0,0 +1,186 @@
+use anyhow::Context;
+use futures::StreamExt;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+
+use crate::{
+    cbz_storage,
+    config::Config,
+    events::RefreshMetadataEvent,
+    extensions::AnyhowErrorToStringChain,
+    hitomi, sidecar,
+    types::{Comic, SidecarFormat},
+};
+
+use super::get_downloaded_comics;
+
+/// The maximum number of gallery infos fetched at the same time
+const REFRESH_CONCURRENCY: usize = 4;
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshReport {
+    pub refreshed: Vec<RefreshedComic>,
+    pub failed: Vec<FailedRefresh>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshedComic {
+    pub id: i32,
+    pub title: String,
+    /// Names of the `Comic` fields that changed on the site, empty if nothing changed
+    pub changed_fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedRefresh {
+    pub id: i32,
+    pub title: String,
+    pub reason: String,
+}
+
+/// Fetch the gallery info of downloaded comics again and rewrite their `metadata.json`
+///
+/// Refresh all downloaded comics if `ids` is `None`
+pub async fn refresh_comics_metadata(app: &AppHandle, ids: Option<Vec<i32>>) -> RefreshReport {
+    let download_dir = app.state::<RwLock<Config>>().read().download_dir.clone();
+    let comics: Vec<Comic> = get_downloaded_comics(&download_dir)
+        .into_iter()
+        .filter(|comic| ids.as_ref().is_none_or(|ids| ids.contains(&comic.id)))
+        .collect();
+
+    let uuid = uuid::Uuid::new_v4().to_string();
+    #[allow(clippy::cast_possible_truncation)]
+    let total = comics.len() as u32;
+    let _ = RefreshMetadataEvent::Start {
+        uuid: uuid.clone(),
+        total,
+    }
+    .emit(app);
+
+    let mut results = futures::stream::iter(comics)
+        .map(|comic| refresh_comic_metadata(app, comic))
+        .buffer_unordered(REFRESH_CONCURRENCY);
+
+    let mut report = RefreshReport::default();
+    let mut current = 0;
+    while let Some(result) = results.next().await {
+        match result {
+            Ok(refreshed_comic) => report.refreshed.push(refreshed_comic),
+            Err(failed_refresh) => report.failed.push(failed_refresh),
+        }
+
+        current += 1;
+        let _ = RefreshMetadataEvent::Progress {
+            uuid: uuid.clone(),
+            current,
+            total,
+        }
+        .emit(app);
+    }
+
+    let _ = RefreshMetadataEvent::End { uuid }.emit(app);
+
+    report
+}
+
+async fn refresh_comic_metadata(
+    app: &AppHandle,
+    comic: Comic,
+) -> Result<RefreshedComic, FailedRefresh> {
+    let id = comic.id;
+    let title = comic.title.clone();
+
+    match try_refresh_comic_metadata(app, &comic).await {
+        Ok(changed_fields) => Ok(RefreshedComic {
+            id,
+            title,
+            changed_fields,
+        }),
+        Err(err) => {
+            let err_title = format!("Failed to refresh metadata of `{title}`");
+            let string_chain = err.to_string_chain();
+            tracing::error!(err_title, message = string_chain);
+            Err(FailedRefresh {
+                id,
+                title,
+                reason: string_chain,
+            })
+        }
+    }
+}
+
+/// Return the names of the fields that changed
+async fn try_refresh_comic_metadata(app: &AppHandle, comic: &Comic) -> anyhow::Result<Vec<String>> {
+    let id = comic.id;
+    let comic_download_dir = comic
+        .comic_download_dir
+        .clone()
+        .context("`comic_download_dir` field is `None`")?;
+
+    let gallery_info = hitomi::get_gallery_info(id)
+        .await
+        .context(format!("Failed to get gallery info for `{id}`"))?;
+    let mut fresh_comic = Comic::from_gallery_info(app, gallery_info)
+        .await
+        .context(format!("Failed to create Comic from gallery_info `{id}`"))?;
+    // These fields describe the local copy rather than the gallery on the site, so keep them as they are.
+    // `cover_url` is also kept, because the subdomain in it changes over time without the cover changing
+    fresh_comic.cover_url.clone_from(&comic.cover_url);
+    fresh_comic.download_format = comic.download_format;
+    fresh_comic.images_deleted = comic.images_deleted;
+    fresh_comic.is_downloaded = comic.is_downloaded;
+    fresh_comic
+        .comic_download_dir
+        .clone_from(&comic.comic_download_dir);
+
+    // The images are not downloaded again, so `files` must keep describing the images on disk
+    fresh_comic.files.clone_from(&comic.files);
+
+    let mut changed_fields = get_changed_fields(comic, &fresh_comic)?;
+    if changed_fields.is_empty() {
+        return Ok(changed_fields);
+    }
+    changed_fields.sort();
+
+    if comic.is_stored_as_cbz() {
+        cbz_storage::rewrite_metadata(&fresh_comic, &comic_download_dir)
+            .context(format!("Failed to save metadata of `{}`", comic.title))?;
+        return Ok(changed_fields);
+    }
+
+    fresh_comic
+        .save_metadata(&comic_download_dir)
+        .context(format!("Failed to save metadata of `{}`", comic.title))?;
+    // Keep the sidecars that already exist in line with `metadata.json`
+    let sidecar_formats: Vec<SidecarFormat> = SidecarFormat::ALL
+        .into_iter()
+        .filter(|format| comic_download_dir.join(format.filename()).exists())
+        .collect();
+    sidecar::write_sidecars(&fresh_comic, &comic_download_dir, &sidecar_formats).context(
+        format!("Failed to write sidecar metadata of `{}`", comic.title),
+    )?;
+
+    Ok(changed_fields)
+}
+
+fn get_changed_fields(old_comic: &Comic, new_comic: &Comic) -> anyhow::Result<Vec<String>> {
+    let old_value = serde_json::to_value(old_comic).context("Failed to convert Comic to json")?;
+    let new_value = serde_json::to_value(new_comic).context("Failed to convert Comic to json")?;
+    let (Some(old_map), Some(new_map)) = (old_value.as_object(), new_value.as_object()) else {
+        return Err(anyhow::anyhow!("Comic is not a JSON object"));
+    };
+
+    let changed_fields = new_map
+        .iter()
+        .filter(|(key, new_field)| old_map.get(*key) != Some(*new_field))
+        .map(|(key, _)| key.clone())
+        .collect();
+
+    Ok(changed_fields)
+}
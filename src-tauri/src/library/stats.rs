@@ -0,0 +1,337 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use walkdir::WalkDir;
+
+use crate::{
+    cbz_storage,
+    extensions::{AnyhowErrorToStringChain, PathIsImg},
+    types::Comic,
+};
+
+use super::{file_identity, get_downloaded_comics};
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryStats {
+    pub comic_count: u32,
+    /// Sum of the sizes of the comics, a page shared through `StorageMode::DeduplicatedFolder` counts in every comic that links it
+    pub total_size: u64,
+    /// Space that `download_dir` takes on disk, hardlinked files are counted once and the page store is included
+    pub disk_size: u64,
+    pub page_count: u64,
+    pub by_format: Vec<StatsEntry>,
+    pub by_artist: Vec<StatsEntry>,
+    pub by_group: Vec<StatsEntry>,
+    pub by_tag: Vec<StatsEntry>,
+    pub by_language: Vec<StatsEntry>,
+    pub by_type: Vec<StatsEntry>,
+    /// Grouped by the month (`YYYY-MM`) in which `metadata.json` was last modified, oldest first
+    pub by_month: Vec<StatsEntry>,
+    pub largest_comics: Vec<ComicSize>,
+    /// Leftover `.downloading-*` directories of unfinished or cancelled download tasks
+    pub leftover_dirs: Vec<LeftoverDir>,
+    pub leftover_size: u64,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsEntry {
+    pub key: String,
+    pub comic_count: u32,
+    pub total_size: u64,
+    pub page_count: u64,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicSize {
+    pub id: i32,
+    pub title: String,
+    pub comic_download_dir: PathBuf,
+    pub size: u64,
+    pub page_count: u64,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct LeftoverDir {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Disk usage of a single comic download directory
+#[derive(Default)]
+struct ComicDiskUsage {
+    size: u64,
+    page_count: u64,
+    /// Image extension -> (size, page count)
+    by_format: HashMap<String, (u64, u64)>,
+    /// Modification time of `metadata.json`, or of the archive for comics stored as CBZ
+    download_time: Option<DateTime<Local>>,
+}
+
+impl ComicDiskUsage {
+    fn new(comic_download_dir: &Path) -> anyhow::Result<ComicDiskUsage> {
+        if cbz_storage::is_cbz_path(comic_download_dir) {
+            return ComicDiskUsage::from_cbz(comic_download_dir);
+        }
+
+        let mut usage = ComicDiskUsage::default();
+
+        let entries = std::fs::read_dir(comic_download_dir).context(format!(
+            "Failed to read directory `{}`",
+            comic_download_dir.display()
+        ))?;
+        for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            let Ok(metadata) = path.metadata() else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            usage.size += metadata.len();
+
+            if path.file_name().is_some_and(|name| name == "metadata.json") {
+                usage.download_time = metadata.modified().ok().map(DateTime::from);
+            }
+
+            if !path.is_img() {
+                continue;
+            }
+            usage.page_count += 1;
+            let extension = path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let format_usage = usage.by_format.entry(extension).or_default();
+            format_usage.0 += metadata.len();
+            format_usage.1 += 1;
+        }
+
+        Ok(usage)
+    }
+
+    /// The archive counts as a whole, its pages are counted by their compressed size
+    fn from_cbz(cbz_path: &Path) -> anyhow::Result<ComicDiskUsage> {
+        let metadata = cbz_path.metadata().context(format!(
+            "Failed to get metadata of `{}`",
+            cbz_path.display()
+        ))?;
+        let mut usage = ComicDiskUsage {
+            size: metadata.len(),
+            download_time: metadata.modified().ok().map(DateTime::from),
+            ..Default::default()
+        };
+
+        let mut zip_archive = cbz_storage::open_zip_archive(cbz_path)?;
+        for i in 0..zip_archive.len() {
+            let zip_file = zip_archive.by_index_raw(i).context(format!(
+                "Failed to read entry `{i}` of `{}`",
+                cbz_path.display()
+            ))?;
+            let path = Path::new(zip_file.name());
+            if !path.is_img() {
+                continue;
+            }
+            usage.page_count += 1;
+            let extension = path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let format_usage = usage.by_format.entry(extension).or_default();
+            format_usage.0 += zip_file.compressed_size();
+            format_usage.1 += 1;
+        }
+
+        Ok(usage)
+    }
+}
+
+/// Accumulate the stats of comics grouped by key
+#[derive(Default)]
+struct StatsGroup(HashMap<String, StatsEntry>);
+
+impl StatsGroup {
+    fn add(&mut self, key: &str, size: u64, page_count: u64) {
+        let entry = self.0.entry(key.to_string()).or_insert_with(|| StatsEntry {
+            key: key.to_string(),
+            ..Default::default()
+        });
+        entry.comic_count += 1;
+        entry.total_size += size;
+        entry.page_count += page_count;
+    }
+
+    /// Sorted by total size, with the largest at the front
+    fn into_sorted_by_size(self) -> Vec<StatsEntry> {
+        let mut entries: Vec<StatsEntry> = self.0.into_values().collect();
+        entries.sort_by(|a, b| {
+            b.total_size
+                .cmp(&a.total_size)
+                .then_with(|| a.key.cmp(&b.key))
+        });
+        entries
+    }
+
+    /// Sorted by key in ascending order
+    fn into_sorted_by_key(self) -> Vec<StatsEntry> {
+        let mut entries: Vec<StatsEntry> = self.0.into_values().collect();
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+        entries
+    }
+}
+
+/// Aggregate the comics in `download_dir` by size, page count and metadata,
+/// and keep only the `top_n` largest comics in `largest_comics`
+pub fn get_library_stats(download_dir: &Path, top_n: usize) -> LibraryStats {
+    let comics = get_downloaded_comics(download_dir);
+
+    let mut stats = LibraryStats::default();
+    let mut by_format = StatsGroup::default();
+    let mut by_artist = StatsGroup::default();
+    let mut by_group = StatsGroup::default();
+    let mut by_tag = StatsGroup::default();
+    let mut by_language = StatsGroup::default();
+    let mut by_type = StatsGroup::default();
+    let mut by_month = StatsGroup::default();
+    let mut comic_sizes = Vec::new();
+
+    for comic in &comics {
+        let Some(comic_download_dir) = comic.comic_download_dir.as_ref() else {
+            continue;
+        };
+        let usage = match ComicDiskUsage::new(comic_download_dir) {
+            Ok(usage) => usage,
+            Err(err) => {
+                let err_title = "An error occurred while getting library stats, skipped";
+                let string_chain = err.to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+                continue;
+            }
+        };
+        let (size, page_count) = (usage.size, usage.page_count);
+
+        stats.comic_count += 1;
+        stats.total_size += size;
+        stats.page_count += page_count;
+
+        for (format, (format_size, format_page_count)) in &usage.by_format {
+            by_format.add(format, *format_size, *format_page_count);
+        }
+        for artist in &comic.artists {
+            by_artist.add(artist, size, page_count);
+        }
+        for group in &comic.groups {
+            by_group.add(group, size, page_count);
+        }
+        for tag in &comic.tags {
+            by_tag.add(&tag.name_with_namespace(), size, page_count);
+        }
+        by_language.add(&language_key(comic), size, page_count);
+        by_type.add(&comic.type_field, size, page_count);
+        if let Some(download_time) = usage.download_time {
+            by_month.add(&download_time.format("%Y-%m").to_string(), size, page_count);
+        }
+
+        comic_sizes.push(ComicSize {
+            id: comic.id,
+            title: comic.title.clone(),
+            comic_download_dir: comic_download_dir.clone(),
+            size,
+            page_count,
+        });
+    }
+
+    comic_sizes.sort_by_key(|comic_size| Reverse(comic_size.size));
+    comic_sizes.truncate(top_n);
+
+    stats.by_format = by_format.into_sorted_by_size();
+    stats.by_artist = by_artist.into_sorted_by_size();
+    stats.by_group = by_group.into_sorted_by_size();
+    stats.by_tag = by_tag.into_sorted_by_size();
+    stats.by_language = by_language.into_sorted_by_size();
+    stats.by_type = by_type.into_sorted_by_size();
+    stats.by_month = by_month.into_sorted_by_key();
+    stats.largest_comics = comic_sizes;
+    stats.disk_size = disk_size(download_dir);
+    stats.leftover_dirs = get_leftover_dirs(download_dir);
+    stats.leftover_size = stats.leftover_dirs.iter().map(|dir| dir.size).sum();
+
+    stats
+}
+
+fn language_key(comic: &Comic) -> String {
+    if comic.language.is_empty() {
+        // Galleries without text, such as image sets, have no language
+        "N/A".to_string()
+    } else {
+        comic.language.clone()
+    }
+}
+
+/// Get all `.downloading-*` directories in `download_dir`
+fn get_leftover_dirs(download_dir: &Path) -> Vec<LeftoverDir> {
+    let mut leftover_dirs = Vec::new();
+
+    let mut walker = WalkDir::new(download_dir).into_iter();
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let is_leftover = entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(".downloading-");
+        if !is_leftover {
+            continue;
+        }
+        // No need to go inside the leftover directory, `dir_size` will do it
+        walker.skip_current_dir();
+
+        leftover_dirs.push(LeftoverDir {
+            path: entry.path().to_path_buf(),
+            size: dir_size(entry.path()),
+        });
+    }
+
+    leftover_dirs.sort_by_key(|leftover_dir| Reverse(leftover_dir.size));
+    leftover_dirs
+}
+
+/// Get the total size of all files in `download_dir`, counting every hardlinked file once
+fn disk_size(download_dir: &Path) -> u64 {
+    let mut counted_files = HashSet::new();
+    WalkDir::new(download_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            // A file that can't be identified is counted, it's more likely a plain file than a link
+            file_identity(entry.path()).map_or(true, |identity| counted_files.insert(identity))
+        })
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Get the total size of all files in `dir`
+pub fn dir_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
@@ -0,0 +1,151 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use zip::ZipArchive;
+
+use crate::{cbz_storage, extensions::PathIsImg};
+
+/// Read access to the files of a downloaded comic, whether it's stored as a folder or as a CBZ archive
+pub enum ComicStorage {
+    Folder(PathBuf),
+    Cbz {
+        cbz_path: PathBuf,
+        zip_archive: ZipArchive<File>,
+    },
+}
+
+impl ComicStorage {
+    /// Open `comic_download_dir`, which is the path of the archive for comics stored as CBZ
+    pub fn open(comic_download_dir: &Path) -> anyhow::Result<ComicStorage> {
+        if cbz_storage::is_cbz_path(comic_download_dir) {
+            let zip_archive = cbz_storage::open_zip_archive(comic_download_dir)?;
+            return Ok(ComicStorage::Cbz {
+                cbz_path: comic_download_dir.to_path_buf(),
+                zip_archive,
+            });
+        }
+        Ok(ComicStorage::Folder(comic_download_dir.to_path_buf()))
+    }
+
+    /// Get the names of all files of the comic, directories are not included
+    pub fn file_names(&self) -> anyhow::Result<Vec<String>> {
+        let file_names = match self {
+            ComicStorage::Folder(dir) => std::fs::read_dir(dir)
+                .context(format!("Failed to read directory `{}`", dir.display()))?
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .filter_map(|path| Some(path.file_name()?.to_string_lossy().to_string()))
+                .collect(),
+            ComicStorage::Cbz { zip_archive, .. } => zip_archive
+                .file_names()
+                .filter(|name| !name.ends_with('/'))
+                .map(ToString::to_string)
+                .collect(),
+        };
+        Ok(file_names)
+    }
+
+    /// Get the names of the pages, sorted in reading order
+    pub fn page_names(&self) -> anyhow::Result<Vec<String>> {
+        let mut page_names: Vec<String> = self
+            .file_names()?
+            .into_iter()
+            .filter(|name| Path::new(name).is_img())
+            .collect();
+        page_names.sort();
+        Ok(page_names)
+    }
+
+    /// Read the whole file `name`
+    pub fn read(&mut self, name: &str) -> anyhow::Result<Vec<u8>> {
+        match self {
+            ComicStorage::Folder(dir) => {
+                let path = dir.join(name);
+                std::fs::read(&path).context(format!("Failed to read `{}`", path.display()))
+            }
+            ComicStorage::Cbz {
+                cbz_path,
+                zip_archive,
+            } => {
+                let mut zip_file = zip_archive.by_name(name).context(format!(
+                    "Failed to find `{name}` in `{}`",
+                    cbz_path.display()
+                ))?;
+                let mut data = Vec::new();
+                zip_file.read_to_end(&mut data).context(format!(
+                    "Failed to read `{name}` in `{}`",
+                    cbz_path.display()
+                ))?;
+                Ok(data)
+            }
+        }
+    }
+
+    /// Read at most the first `max_len` bytes of the file `name`, which is enough to check the header of an image
+    pub fn read_prefix(&mut self, name: &str, max_len: u64) -> anyhow::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        match self {
+            ComicStorage::Folder(dir) => {
+                let path = dir.join(name);
+                File::open(&path)
+                    .context(format!("Failed to open `{}`", path.display()))?
+                    .take(max_len)
+                    .read_to_end(&mut data)
+                    .context(format!("Failed to read `{}`", path.display()))?;
+            }
+            ComicStorage::Cbz {
+                cbz_path,
+                zip_archive,
+            } => {
+                zip_archive
+                    .by_name(name)
+                    .context(format!(
+                        "Failed to find `{name}` in `{}`",
+                        cbz_path.display()
+                    ))?
+                    .take(max_len)
+                    .read_to_end(&mut data)
+                    .context(format!(
+                        "Failed to read `{name}` in `{}`",
+                        cbz_path.display()
+                    ))?;
+            }
+        }
+        Ok(data)
+    }
+
+    /// Get the uncompressed size of the file `name` without reading it
+    pub fn size(&mut self, name: &str) -> anyhow::Result<u64> {
+        match self {
+            ComicStorage::Folder(dir) => {
+                let path = dir.join(name);
+                let metadata = std::fs::metadata(&path)
+                    .context(format!("Failed to get metadata of `{}`", path.display()))?;
+                Ok(metadata.len())
+            }
+            ComicStorage::Cbz {
+                cbz_path,
+                zip_archive,
+            } => {
+                let zip_file = zip_archive.by_name(name).context(format!(
+                    "Failed to find `{name}` in `{}`",
+                    cbz_path.display()
+                ))?;
+                Ok(zip_file.size())
+            }
+        }
+    }
+
+    /// Where the file `name` lives, used in error messages
+    pub fn display_path(&self, name: &str) -> String {
+        match self {
+            ComicStorage::Folder(dir) => dir.join(name).display().to_string(),
+            ComicStorage::Cbz { cbz_path, .. } => format!("{}/{name}", cbz_path.display()),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    config::Config, export, extensions::AnyhowErrorToStringChain, types::Comic, utils::move_path,
+};
+
+const TRASH_INFO_FILENAME: &str = "trash-info.json";
+const TRASH_COMIC_DIR_NAME: &str = "comic";
+const TRASH_EXPORTS_DIR_NAME: &str = "exports";
+
+/// A comic that has been moved to the trash
+///
+/// Each trashed comic has its own directory under the trash directory:
+/// - `trash-info.json`: serialized `TrashedComic`
+/// - `comic`: the original `comic_download_dir`
+/// - `exports`: the export files that were trashed along with the comic
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedComic {
+    pub trash_id: String,
+    pub comic: Comic,
+    pub original_download_dir: PathBuf,
+    pub original_export_paths: Vec<PathBuf>,
+    pub trashed_at: String,
+}
+
+pub fn trash_dir(app: &AppHandle) -> anyhow::Result<PathBuf> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .context("failed to get app_data_dir")?;
+    Ok(app_data_dir.join("trash"))
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashReport {
+    pub trashed: Vec<TrashedComic>,
+    pub failed: Vec<FailedTrash>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedTrash {
+    pub id: i32,
+    pub title: String,
+    pub reason: String,
+}
+
+/// Move `comics` to the trash one by one, a comic that fails doesn't stop the others
+pub fn move_comics_to_trash(
+    app: &AppHandle,
+    comics: &[Comic],
+    include_exports: bool,
+) -> TrashReport {
+    let mut report = TrashReport::default();
+    for comic in comics {
+        match move_comic_to_trash(app, comic, include_exports) {
+            Ok(trashed_comic) => report.trashed.push(trashed_comic),
+            Err(err) => {
+                let err_title = format!("Failed to move `{}` to the trash", comic.title);
+                let string_chain = err.to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+                report.failed.push(FailedTrash {
+                    id: comic.id,
+                    title: comic.title.clone(),
+                    reason: string_chain,
+                });
+            }
+        }
+    }
+    report
+}
+
+/// Move the download directory of `comic` to the trash, along with its export files if `include_exports` is true
+pub fn move_comic_to_trash(
+    app: &AppHandle,
+    comic: &Comic,
+    include_exports: bool,
+) -> anyhow::Result<TrashedComic> {
+    let comic_title = &comic.title;
+
+    let comic_download_dir = comic
+        .comic_download_dir
+        .clone()
+        .context("`comic_download_dir` field is `None`")?;
+
+    let original_export_paths = if include_exports {
+        export::get_export_paths(app, comic)
+            .context(format!("Failed to get export paths of `{comic_title}`"))?
+            .into_iter()
+            .filter(|path| path.is_file())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let trash_dir = trash_dir(app)?;
+    std::fs::create_dir_all(&trash_dir).context(format!(
+        "Failed to create directory `{}`",
+        trash_dir.display()
+    ))?;
+    let trash_id = uuid::Uuid::new_v4().to_string();
+    let trash_entry_dir = trash_dir.join(&trash_id);
+    // `create_dir` fails if the entry already exists, so nothing is ever moved into another trashed comic
+    std::fs::create_dir(&trash_entry_dir).context(format!(
+        "Failed to create directory `{}`",
+        trash_entry_dir.display()
+    ))?;
+
+    let trashed_comic = TrashedComic {
+        trash_id,
+        comic: comic.clone(),
+        original_download_dir: comic_download_dir.clone(),
+        original_export_paths,
+        trashed_at: chrono::Local::now().to_rfc3339(),
+    };
+    // Write the trash info first, so that the entry can always be restored, even if moving is interrupted
+    let trash_info_json = serde_json::to_string_pretty(&trashed_comic)
+        .context("Failed to serialize TrashedComic to json")?;
+    let trash_info_path = trash_entry_dir.join(TRASH_INFO_FILENAME);
+    std::fs::write(&trash_info_path, trash_info_json).context(format!(
+        "Failed to write json to `{}`",
+        trash_info_path.display()
+    ))?;
+
+    let trashed_comic_dir = trash_entry_dir.join(TRASH_COMIC_DIR_NAME);
+    move_path(&comic_download_dir, &trashed_comic_dir).context(format!(
+        "Failed to move `{}` to the trash",
+        comic_download_dir.display()
+    ))?;
+    let download_dir = app.state::<RwLock<Config>>().read().download_dir.clone();
+    remove_empty_parent_dirs(&comic_download_dir, &download_dir);
+
+    for export_path in &trashed_comic.original_export_paths {
+        let trashed_export_path = trashed_export_path(&trash_entry_dir, export_path)?;
+        move_path(export_path, &trashed_export_path).context(format!(
+            "Failed to move `{}` to the trash",
+            export_path.display()
+        ))?;
+    }
+
+    tracing::debug!(
+        id = comic.id,
+        comic_title,
+        "Moved `{}` to the trash",
+        comic_download_dir.display()
+    );
+
+    Ok(trashed_comic)
+}
+
+/// Get all comics in the trash, with the most recently trashed at the front
+pub fn get_trashed_comics(app: &AppHandle) -> anyhow::Result<Vec<TrashedComic>> {
+    let trash_dir = trash_dir(app)?;
+    if !trash_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = std::fs::read_dir(&trash_dir).context(format!(
+        "Failed to read directory `{}`",
+        trash_dir.display()
+    ))?;
+
+    let mut trashed_comics = Vec::new();
+    for trash_entry_dir in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+        match read_trash_info(&trash_entry_dir) {
+            Ok(trashed_comic) => trashed_comics.push(trashed_comic),
+            Err(err) => {
+                let err_title = "An error occurred while getting trashed comics, skipped";
+                let string_chain = err.to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+            }
+        }
+    }
+    // rfc3339 timestamps with the same offset can be compared as strings
+    trashed_comics.sort_by(|a, b| b.trashed_at.cmp(&a.trashed_at));
+
+    Ok(trashed_comics)
+}
+
+/// Move the trashed comic back to where it was, return the restored comic
+pub fn restore_trashed_comic(app: &AppHandle, trash_id: &str) -> anyhow::Result<Comic> {
+    let trash_entry_dir = trash_dir(app)?.join(trash_id);
+    let trashed_comic = read_trash_info(&trash_entry_dir)?;
+    let comic_title = &trashed_comic.comic.title;
+
+    let original_download_dir = &trashed_comic.original_download_dir;
+    if original_download_dir.exists() {
+        return Err(anyhow!(
+            "Failed to restore `{comic_title}`, `{}` already exists",
+            original_download_dir.display()
+        ));
+    }
+
+    let trashed_comic_dir = trash_entry_dir.join(TRASH_COMIC_DIR_NAME);
+    move_path(&trashed_comic_dir, original_download_dir).context(format!(
+        "Failed to move `{}` back to `{}`",
+        trashed_comic_dir.display(),
+        original_download_dir.display()
+    ))?;
+
+    for export_path in &trashed_comic.original_export_paths {
+        let trashed_export_path = trashed_export_path(&trash_entry_dir, export_path)?;
+        if !trashed_export_path.exists() {
+            continue;
+        }
+        if export_path.exists() {
+            // The comic has been exported again after it was trashed, keep the newer export
+            tracing::warn!(
+                comic_title,
+                "`{}` already exists, skipped restoring it",
+                export_path.display()
+            );
+            continue;
+        }
+        move_path(&trashed_export_path, export_path).context(format!(
+            "Failed to move `{}` back to `{}`",
+            trashed_export_path.display(),
+            export_path.display()
+        ))?;
+    }
+
+    std::fs::remove_dir_all(&trash_entry_dir).context(format!(
+        "Failed to delete directory `{}`",
+        trash_entry_dir.display()
+    ))?;
+
+    let metadata_path = original_download_dir.join("metadata.json");
+    let comic = Comic::from_metadata(&metadata_path).context(format!(
+        "Failed to create Comic from metadata `{}`",
+        metadata_path.display()
+    ))?;
+
+    Ok(comic)
+}
+
+/// Permanently delete the trashed comic
+pub fn delete_trashed_comic(app: &AppHandle, trash_id: &str) -> anyhow::Result<()> {
+    let trash_entry_dir = trash_dir(app)?.join(trash_id);
+    if !trash_entry_dir.exists() {
+        return Err(anyhow!("Can't find trashed comic with id `{trash_id}`"));
+    }
+
+    std::fs::remove_dir_all(&trash_entry_dir).context(format!(
+        "Failed to delete directory `{}`",
+        trash_entry_dir.display()
+    ))?;
+
+    Ok(())
+}
+
+fn read_trash_info(trash_entry_dir: &Path) -> anyhow::Result<TrashedComic> {
+    let trash_info_path = trash_entry_dir.join(TRASH_INFO_FILENAME);
+    let trash_info_json = std::fs::read_to_string(&trash_info_path)
+        .context(format!("Failed to read `{}`", trash_info_path.display()))?;
+    let trashed_comic = serde_json::from_str(&trash_info_json).context(format!(
+        "Failed to deserialize `{}` to TrashedComic",
+        trash_info_path.display()
+    ))?;
+    Ok(trashed_comic)
+}
+
+fn trashed_export_path(trash_entry_dir: &Path, export_path: &Path) -> anyhow::Result<PathBuf> {
+    let filename = export_path.file_name().context(format!(
+        "Failed to get filename of `{}`",
+        export_path.display()
+    ))?;
+    Ok(trash_entry_dir.join(TRASH_EXPORTS_DIR_NAME).join(filename))
+}
+
+/// Delete the empty parent directories of `path` created by `dir_fmt`, stopping at `root`
+fn remove_empty_parent_dirs(path: &Path, root: &Path) {
+    let mut dir = path.parent();
+    while let Some(current_dir) = dir {
+        if current_dir == root || !current_dir.starts_with(root) {
+            break;
+        }
+        // `remove_dir` fails if the directory is not empty, which is exactly when we should stop
+        if std::fs::remove_dir(current_dir).is_err() {
+            break;
+        }
+        dir = current_dir.parent();
+    }
+}
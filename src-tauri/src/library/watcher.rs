@@ -0,0 +1,269 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use notify::{
+    event::{ModifyKind, RenameMode},
+    EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use parking_lot::{Mutex, RwLock};
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use walkdir::WalkDir;
+
+use crate::{
+    config::Config, events::LibraryChangedEvent, extensions::AnyhowErrorToStringChain, types::Comic,
+};
+
+/// How long to wait for the file system to settle before reporting changes,
+/// a single operation in the file manager often produces a burst of events
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(500);
+
+/// Watch `Config::download_dir` for `metadata.json` files and comic CBZ archives being added, removed or edited
+pub struct LibraryWatcher {
+    app: AppHandle,
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl LibraryWatcher {
+    pub fn new(app: &AppHandle) -> Self {
+        Self {
+            app: app.clone(),
+            watcher: Mutex::new(None),
+        }
+    }
+
+    /// Start watching the current `Config::download_dir`, and stop watching the previous one
+    pub fn restart(&self) -> anyhow::Result<()> {
+        // Dropping the previous watcher closes its channel, which ends its event loop
+        *self.watcher.lock() = None;
+
+        let download_dir = self
+            .app
+            .state::<RwLock<Config>>()
+            .read()
+            .download_dir
+            .clone();
+        std::fs::create_dir_all(&download_dir).context(format!(
+            "Failed to create directory `{}`",
+            download_dir.display()
+        ))?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let event_handler = move |res: notify::Result<notify::Event>| {
+            // The receiver is only gone when the watcher is being replaced, so the error can be ignored
+            let _ = sender.send(res);
+        };
+        let mut watcher = RecommendedWatcher::new(event_handler, notify::Config::default())
+            .context("Failed to create library watcher")?;
+        watcher
+            .watch(&download_dir, RecursiveMode::Recursive)
+            .context(format!("Failed to watch `{}`", download_dir.display()))?;
+
+        tauri::async_runtime::spawn(library_watch_loop(
+            self.app.clone(),
+            receiver,
+            download_dir.clone(),
+        ));
+
+        *self.watcher.lock() = Some(watcher);
+        tracing::debug!("Watching `{}` for library changes", download_dir.display());
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct LibraryChanges {
+    added_ids: BTreeSet<i32>,
+    removed_ids: BTreeSet<i32>,
+    updated_ids: BTreeSet<i32>,
+}
+
+async fn library_watch_loop(
+    app: AppHandle,
+    mut receiver: UnboundedReceiver<notify::Result<notify::Event>>,
+    download_dir: PathBuf,
+) {
+    // Walking a large library takes a while, so it's done off the async runtime.
+    // Events that arrive meanwhile wait in the channel
+    let mut metadata_ids =
+        match tauri::async_runtime::spawn_blocking(move || index_metadata_ids(&download_dir)).await
+        {
+            Ok(metadata_ids) => metadata_ids,
+            Err(err) => {
+                let err_title = "Failed to index the library for the library watcher";
+                let string_chain = anyhow::Error::from(err).to_string_chain();
+                tracing::error!(err_title, message = string_chain);
+                return;
+            }
+        };
+    while let Some(res) = receiver.recv().await {
+        let mut results = vec![res];
+        while let Ok(Some(res)) = tokio::time::timeout(DEBOUNCE_DURATION, receiver.recv()).await {
+            results.push(res);
+        }
+
+        let mut changes = LibraryChanges::default();
+        for res in results {
+            match res.map_err(anyhow::Error::from) {
+                Ok(event) => handle_event(&event, &mut metadata_ids, &mut changes),
+                Err(err) => {
+                    let err_title = "Failed to receive library watcher event";
+                    let string_chain = err.to_string_chain();
+                    tracing::error!(err_title, message = string_chain);
+                }
+            }
+        }
+        // A comic that was removed and added again in the same burst has just been moved
+        let LibraryChanges {
+            added_ids,
+            mut removed_ids,
+            mut updated_ids,
+        } = changes;
+        removed_ids.retain(|id| !added_ids.contains(id));
+        updated_ids.retain(|id| !added_ids.contains(id) && !removed_ids.contains(id));
+
+        if added_ids.is_empty() && removed_ids.is_empty() && updated_ids.is_empty() {
+            continue;
+        }
+
+        let _ = LibraryChangedEvent {
+            added_ids: added_ids.into_iter().collect(),
+            removed_ids: removed_ids.into_iter().collect(),
+            updated_ids: updated_ids.into_iter().collect(),
+        }
+        .emit(&app);
+    }
+}
+
+fn handle_event(
+    event: &notify::Event,
+    metadata_ids: &mut HashMap<PathBuf, i32>,
+    changes: &mut LibraryChanges,
+) {
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            handle_paths_added(&event.paths, metadata_ids, changes);
+        }
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            handle_paths_removed(&event.paths, metadata_ids, changes);
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            // The first path is where it was moved from, the second is where it was moved to
+            if let [from, to] = event.paths.as_slice() {
+                handle_paths_removed(std::slice::from_ref(from), metadata_ids, changes);
+                handle_paths_added(std::slice::from_ref(to), metadata_ids, changes);
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(_)) => {
+            // Some platforms don't tell which side of the rename the path is on
+            let (existing_paths, missing_paths): (Vec<PathBuf>, Vec<PathBuf>) =
+                event.paths.iter().cloned().partition(|path| path.exists());
+            handle_paths_removed(&missing_paths, metadata_ids, changes);
+            handle_paths_added(&existing_paths, metadata_ids, changes);
+        }
+        EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any) => {
+            handle_paths_modified(&event.paths, metadata_ids, changes);
+        }
+        _ => {}
+    }
+}
+
+fn handle_paths_added(
+    paths: &[PathBuf],
+    metadata_ids: &mut HashMap<PathBuf, i32>,
+    changes: &mut LibraryChanges,
+) {
+    for path in paths {
+        // A whole comic folder may have been moved in, so look for metadata files inside it
+        for metadata_path in find_metadata_paths(path) {
+            match Comic::from_metadata(&metadata_path) {
+                Ok(comic) => {
+                    metadata_ids.insert(metadata_path, comic.id);
+                    changes.added_ids.insert(comic.id);
+                }
+                Err(err) => {
+                    // The metadata file may still be being written, it will be picked up by the next event
+                    let string_chain = err.to_string_chain();
+                    tracing::debug!(message = string_chain, "Skipped unreadable metadata file");
+                }
+            }
+        }
+    }
+}
+
+fn handle_paths_modified(
+    paths: &[PathBuf],
+    metadata_ids: &mut HashMap<PathBuf, i32>,
+    changes: &mut LibraryChanges,
+) {
+    for path in paths.iter().filter(|path| Comic::is_metadata_path(path)) {
+        let comic = match Comic::from_metadata(path) {
+            Ok(comic) => comic,
+            Err(err) => {
+                // The metadata file may still be being written, it will be picked up by the next event
+                let string_chain = err.to_string_chain();
+                tracing::debug!(message = string_chain, "Skipped unreadable metadata file");
+                continue;
+            }
+        };
+        match metadata_ids.insert(path.clone(), comic.id) {
+            Some(old_id) if old_id == comic.id => {
+                changes.updated_ids.insert(comic.id);
+            }
+            // The ID itself was edited, which makes it a different comic
+            Some(old_id) => {
+                changes.removed_ids.insert(old_id);
+                changes.added_ids.insert(comic.id);
+            }
+            None => {
+                changes.added_ids.insert(comic.id);
+            }
+        }
+    }
+}
+
+fn handle_paths_removed(
+    paths: &[PathBuf],
+    metadata_ids: &mut HashMap<PathBuf, i32>,
+    changes: &mut LibraryChanges,
+) {
+    for path in paths {
+        // The metadata file can't be read anymore, so the ID has to come from the index
+        let removed_metadata_paths: Vec<PathBuf> = metadata_ids
+            .keys()
+            .filter(|metadata_path| metadata_path.starts_with(path))
+            .cloned()
+            .collect();
+        for metadata_path in removed_metadata_paths {
+            if let Some(id) = metadata_ids.remove(&metadata_path) {
+                changes.removed_ids.insert(id);
+            }
+        }
+    }
+}
+
+/// Map the path of every `metadata.json` in `download_dir` to its comic ID
+fn index_metadata_ids(download_dir: &Path) -> HashMap<PathBuf, i32> {
+    find_metadata_paths(download_dir)
+        .into_iter()
+        .filter_map(|metadata_path| {
+            let comic = Comic::from_metadata(&metadata_path).ok()?;
+            Some((metadata_path, comic.id))
+        })
+        .collect()
+}
+
+fn find_metadata_paths(path: &Path) -> Vec<PathBuf> {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| Comic::is_metadata_path(entry.path()))
+        .map(walkdir::DirEntry::into_path)
+        .collect()
+}
@@ -0,0 +1,99 @@
+use std::{fmt::Write, path::Path};
+
+use anyhow::Context;
+
+use crate::types::{Comic, ComicInfo, SidecarFormat};
+
+/// Write `comic` in each of `formats` into `comic_download_dir`, overwriting existing files
+pub fn write_sidecars(
+    comic: &Comic,
+    comic_download_dir: &Path,
+    formats: &[SidecarFormat],
+) -> anyhow::Result<()> {
+    let comic_title = &comic.title;
+
+    for format in formats {
+        let content = render_sidecar(comic, *format)?;
+        let sidecar_path = comic_download_dir.join(format.filename());
+        std::fs::write(&sidecar_path, content).context(format!(
+            "Failed to write `{}` of `{comic_title}`",
+            sidecar_path.display()
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Get the content of the `format` sidecar of `comic`
+pub fn render_sidecar(comic: &Comic, format: SidecarFormat) -> anyhow::Result<String> {
+    let content = match format {
+        SidecarFormat::ComicInfoXml => ComicInfo::from(comic.clone()).to_xml()?,
+        SidecarFormat::GalleryDlJson => gallery_dl_json(comic)?,
+        SidecarFormat::HDoujinTxt => hdoujin_txt(comic),
+    };
+    Ok(content)
+}
+
+/// Same fields as the gallery metadata of gallery-dl's hitomi extractor
+fn gallery_dl_json(comic: &Comic) -> anyhow::Result<String> {
+    let date = comic
+        .parse_date()
+        .map(|date| date.naive_utc().format("%Y-%m-%d %H:%M:%S").to_string());
+    let tags: Vec<String> = comic
+        .tags
+        .iter()
+        .map(|tag| tag.name_with_namespace())
+        .collect();
+
+    let info = serde_json::json!({
+        "category": "hitomi",
+        "subcategory": "gallery",
+        "gallery_id": comic.id,
+        "title": comic.title,
+        "title_jpn": comic.japanese_title,
+        "type": comic.type_field,
+        "language": comic.language,
+        "lang": comic.language_iso(),
+        "date": date,
+        "tags": tags,
+        "artist": comic.artists,
+        "group": comic.groups,
+        "parody": comic.parodys,
+        "characters": comic.characters,
+        "count": comic.files.len(),
+        "url": comic.gallery_url(),
+    });
+
+    serde_json::to_string_pretty(&info).context("Failed to serialize `info.json`")
+}
+
+/// `Key: value` lines in the style of HDoujin Downloader
+fn hdoujin_txt(comic: &Comic) -> String {
+    let tags: Vec<String> = comic
+        .tags
+        .iter()
+        .map(|tag| tag.name_with_namespace())
+        .collect();
+
+    let lines = [
+        ("Title", comic.title.clone()),
+        ("Original Title", comic.japanese_title.clone()),
+        ("URL", comic.gallery_url()),
+        ("Artist", comic.artists.join(", ")),
+        ("Circle", comic.groups.join(", ")),
+        ("Parody", comic.parodys.join(", ")),
+        ("Characters", comic.characters.join(", ")),
+        ("Tags", tags.join(", ")),
+        ("Language", comic.language.clone()),
+        ("Category", comic.type_field.clone()),
+        ("Pages", comic.files.len().to_string()),
+        ("Posted", comic.date.clone()),
+    ];
+
+    lines
+        .iter()
+        .fold(String::new(), |mut output, (key, value)| {
+            let _ = writeln!(output, "{key}: {value}");
+            output
+        })
+}
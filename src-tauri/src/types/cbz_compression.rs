@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// How images are compressed in exported CBZ files
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum CbzCompression {
+    /// Images are already compressed, storing them is faster and the archive is barely larger
+    Store,
+    #[default]
+    Deflate,
+}
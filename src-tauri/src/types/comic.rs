@@ -1,6 +1,7 @@
 use std::path::{Path, PathBuf};
 
 use anyhow::Context;
+use chrono::{DateTime, FixedOffset};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use specta::Type;
@@ -8,8 +9,10 @@ use tauri::{AppHandle, Manager};
 use walkdir::WalkDir;
 
 use crate::{
+    cbz_storage,
     config::Config,
     hitomi::{url_from_url_from_hash, GalleryFiles, GalleryInfo},
+    types::DownloadFormat,
 };
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
@@ -34,6 +37,12 @@ pub struct Comic {
     pub scene_indexes: Vec<i32>,
     pub files: Vec<GalleryFiles>,
     pub cover_url: String,
+    /// The format the images were downloaded in, only recorded in the metadata file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_format: Option<DownloadFormat>,
+    /// Whether the images were deleted after auto export on purpose, only the metadata files are left in that case
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images_deleted: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_downloaded: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -126,6 +135,8 @@ impl Comic {
             scene_indexes: gallery_info.scene_indexes,
             files: gallery_info.files,
             cover_url,
+            download_format: None,
+            images_deleted: None,
             is_downloaded: None,
             comic_download_dir: None,
         };
@@ -138,8 +149,16 @@ impl Comic {
         Ok(comic)
     }
 
+    /// Create a Comic from `metadata_path`, which is either a `metadata.json`
+    /// or a CBZ archive with `metadata.json` inside, as stored by `StorageMode::Cbz`
     pub fn from_metadata(metadata_path: &Path) -> anyhow::Result<Comic> {
-        let comic_json = std::fs::read_to_string(metadata_path).context(format!(
+        let is_cbz = cbz_storage::is_cbz_path(metadata_path);
+        let comic_json = if is_cbz {
+            cbz_storage::read_metadata(metadata_path)
+        } else {
+            std::fs::read_to_string(metadata_path).map_err(anyhow::Error::from)
+        }
+        .context(format!(
             "Failed to convert metadata to Comic, failed to read metadata file `{}`",
             metadata_path.display()
         ))?;
@@ -148,15 +167,70 @@ impl Comic {
             metadata_path.display()
         ))?;
         // The `is_downloaded` and `comic_download_dir` fields are not serialized in the metadata file
-        let parent = metadata_path.parent().context(format!(
-            "Failed to get parent directory of `{}`",
-            metadata_path.display()
-        ))?;
-        comic.comic_download_dir = Some(parent.to_path_buf());
+        let comic_download_dir = if is_cbz {
+            // The archive takes the place of the comic download directory
+            metadata_path
+        } else {
+            metadata_path.parent().context(format!(
+                "Failed to get parent directory of `{}`",
+                metadata_path.display()
+            ))?
+        };
+        comic.comic_download_dir = Some(comic_download_dir.to_path_buf());
         comic.is_downloaded = Some(true);
         Ok(comic)
     }
 
+    /// Whether `path` holds the metadata of a downloaded comic,
+    /// which is a `metadata.json` or a CBZ archive with `metadata.json` inside
+    pub fn is_metadata_path(path: &Path) -> bool {
+        if !path.is_file() {
+            return false;
+        }
+        if path
+            .file_name()
+            .is_some_and(|name| name == cbz_storage::METADATA_FILENAME)
+        {
+            return true;
+        }
+        cbz_storage::is_cbz_path(path) && cbz_storage::contains_metadata(path)
+    }
+
+    /// Whether the comic is stored as a CBZ archive, in which case `comic_download_dir` is the path of the archive
+    pub fn is_stored_as_cbz(&self) -> bool {
+        self.comic_download_dir
+            .as_ref()
+            .is_some_and(|comic_download_dir| cbz_storage::is_cbz_path(comic_download_dir))
+    }
+
+    /// Save the comic as the metadata file `metadata.json` in `comic_download_dir`
+    pub fn save_metadata(&self, comic_download_dir: &Path) -> anyhow::Result<()> {
+        let comic_title = &self.title;
+        let comic_json = self
+            .to_metadata_json()
+            .context(format!("Failed to save metadata of `{comic_title}`"))?;
+
+        let metadata_path = comic_download_dir.join("metadata.json");
+
+        std::fs::write(&metadata_path, comic_json).context(format!(
+            "Failed to save metadata of `{comic_title}`, Failed to write json to `{}`",
+            metadata_path.display()
+        ))?;
+
+        Ok(())
+    }
+
+    /// Serialize the comic to the content of `metadata.json`
+    pub fn to_metadata_json(&self) -> anyhow::Result<String> {
+        let mut comic = self.clone();
+        // Set the `is_downloaded` and `comic_download_dir` field to `None`
+        // so that the `is_downloaded` and `comic_download_dir` field is ignored during serialization
+        comic.is_downloaded = None;
+        comic.comic_download_dir = None;
+
+        serde_json::to_string_pretty(&comic).context("Failed to serialize Comic to json")
+    }
+
     /// Update fields based on the metadata file in the download directory
     ///
     /// Update fields and logic:
@@ -173,15 +247,16 @@ impl Comic {
             .filter_map(Result::ok)
         {
             let path = entry.path();
-            if path.is_dir() {
-                continue;
-            }
-            if entry.file_name() != "metadata.json" {
+            if !Comic::is_metadata_path(path) {
                 continue;
             }
-            // now the entry is the metadata.json file
-            let metadata_str = std::fs::read_to_string(path)
-                .context(format!("Failed to read `{}`", path.display()))?;
+            // now the entry is the metadata.json file or a CBZ archive containing it
+            let metadata_str = if cbz_storage::is_cbz_path(path) {
+                cbz_storage::read_metadata(path)?
+            } else {
+                std::fs::read_to_string(path)
+                    .context(format!("Failed to read `{}`", path.display()))?
+            };
 
             let comic_json: serde_json::Value =
                 serde_json::from_str(&metadata_str).context(format!(
@@ -205,12 +280,16 @@ impl Comic {
                 continue;
             }
 
-            let parent = path.parent().context(format!(
-                "Failed to get parent directory of `{}`",
-                path.display()
-            ))?;
+            let comic_download_dir = if cbz_storage::is_cbz_path(path) {
+                path
+            } else {
+                path.parent().context(format!(
+                    "Failed to get parent directory of `{}`",
+                    path.display()
+                ))?
+            };
 
-            self.comic_download_dir = Some(parent.to_path_buf());
+            self.comic_download_dir = Some(comic_download_dir.to_path_buf());
             self.is_downloaded = Some(true);
             break;
         }
@@ -224,8 +303,13 @@ impl Comic {
             .as_ref()
             .context("`comic_download_dir` field is `None`")?;
 
-        let comic_download_dir_name = comic_download_dir
-            .file_name()
+        // The name of an archive is the name its comic download directory would have
+        let comic_download_dir_name = if self.is_stored_as_cbz() {
+            comic_download_dir.file_stem()
+        } else {
+            comic_download_dir.file_name()
+        };
+        let comic_download_dir_name = comic_download_dir_name
             .context(format!(
                 "Failed to get directory name of `{}`",
                 comic_download_dir.display()
@@ -256,10 +340,62 @@ impl Comic {
                 download_dir.display()
             ))?;
 
-        let comic_export_dir = export_dir.join(relative_dir);
+        let comic_export_dir = if self.is_stored_as_cbz() {
+            export_dir.join(relative_dir.with_extension(""))
+        } else {
+            export_dir.join(relative_dir)
+        };
         Ok(comic_export_dir)
     }
 
+    /// Link to the gallery on hitomi
+    pub fn gallery_url(&self) -> String {
+        format!("https://hitomi.la/galleries/{}.html", self.id)
+    }
+
+    /// Parse `date`, which looks like `2024-01-08 03:52:00-06`
+    pub fn parse_date(&self) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_str(&self.date, "%Y-%m-%d %H:%M:%S%#z").ok()
+    }
+
+    /// ISO 639-1 code of `language`, `None` if the language is unknown or the gallery has no text
+    pub fn language_iso(&self) -> Option<&'static str> {
+        let language_iso = match self.language.as_str() {
+            "english" => "en",
+            "japanese" => "ja",
+            "chinese" => "zh",
+            "korean" => "ko",
+            "spanish" => "es",
+            "french" => "fr",
+            "german" => "de",
+            "russian" => "ru",
+            "italian" => "it",
+            "portuguese" => "pt",
+            "polish" => "pl",
+            "thai" => "th",
+            "vietnamese" => "vi",
+            "indonesian" => "id",
+            "tagalog" => "tl",
+            "turkish" => "tr",
+            "ukrainian" => "uk",
+            "arabic" => "ar",
+            "hungarian" => "hu",
+            "czech" => "cs",
+            "dutch" => "nl",
+            "greek" => "el",
+            "finnish" => "fi",
+            "swedish" => "sv",
+            "norwegian" => "no",
+            "danish" => "da",
+            "hebrew" => "he",
+            "persian" => "fa",
+            "romanian" => "ro",
+            "malay" => "ms",
+            _ => return None,
+        };
+        Some(language_iso)
+    }
+
     pub fn get_temp_download_dir(&self) -> anyhow::Result<PathBuf> {
         let comic_download_dir = self
             .comic_download_dir
@@ -288,6 +424,19 @@ pub struct Tag {
     pub male: i32,
 }
 
+impl Tag {
+    /// Tag name with its namespace, e.g. `female:glasses`, `male:shota` or `full color`
+    pub fn name_with_namespace(&self) -> String {
+        if self.female == 1 {
+            format!("female:{}", self.tag)
+        } else if self.male == 1 {
+            format!("male:{}", self.tag)
+        } else {
+            self.tag.clone()
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize, Type)]
 #[allow(clippy::struct_field_names)]
 pub struct Language {
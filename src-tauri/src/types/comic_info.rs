@@ -1,68 +1,334 @@
+use anyhow::anyhow;
+use chrono::Datelike;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 use yaserde::{YaDeserialize, YaSerialize};
 
-use super::Comic;
+use super::{Comic, Tag};
 
 /// https://wiki.kavitareader.com/guides/metadata/comics/
+///
+/// Fields are in the order of the `ComicInfo.xsd` v2.1 sequence, `None` fields are not written
 #[derive(
     Default, Debug, Clone, PartialEq, Serialize, Deserialize, Type, YaSerialize, YaDeserialize,
 )]
 #[serde(rename_all = "camelCase")]
 pub struct ComicInfo {
-    #[yaserde(rename = "Manga")]
-    pub manga: String,
+    /// Title of this chapter
+    #[yaserde(rename = "Title")]
+    pub title: Option<String>,
     /// Comic title
     #[yaserde(rename = "Series")]
     pub series: String,
+    /// Normal chapter number
+    #[yaserde(rename = "Number")]
+    pub number: Option<String>,
+    /// Total number of chapters
+    /// - `0` => Ongoing
+    /// - `Non-zero` and consistent with `Number` or `Volume` => Completed
+    /// - `Other non-zero values` => Ended
+    #[yaserde(rename = "Count")]
+    pub count: i64,
+    /// Volume number
+    #[yaserde(rename = "Volume")]
+    pub volume: Option<String>,
+    /// The Japanese title
+    #[yaserde(rename = "AlternateSeries")]
+    pub alternate_series: Option<String>,
+    #[yaserde(rename = "AlternateNumber")]
+    pub alternate_number: Option<String>,
+    #[yaserde(rename = "AlternateCount")]
+    pub alternate_count: Option<i64>,
+    #[yaserde(rename = "Summary")]
+    pub summary: Option<String>,
+    #[yaserde(rename = "Notes")]
+    pub notes: Option<String>,
+    #[yaserde(rename = "Year")]
+    pub year: Option<i32>,
+    #[yaserde(rename = "Month")]
+    pub month: Option<u32>,
+    #[yaserde(rename = "Day")]
+    pub day: Option<u32>,
     #[yaserde(rename = "Writer")]
     pub writer: String,
+    #[yaserde(rename = "Penciller")]
+    pub penciller: Option<String>,
+    #[yaserde(rename = "Inker")]
+    pub inker: Option<String>,
+    #[yaserde(rename = "Colorist")]
+    pub colorist: Option<String>,
+    #[yaserde(rename = "Letterer")]
+    pub letterer: Option<String>,
+    #[yaserde(rename = "CoverArtist")]
+    pub cover_artist: Option<String>,
+    #[yaserde(rename = "Editor")]
+    pub editor: Option<String>,
+    #[yaserde(rename = "Translator")]
+    pub translator: Option<String>,
     #[yaserde(rename = "Publisher")]
     pub publisher: String,
+    #[yaserde(rename = "Imprint")]
+    pub imprint: Option<String>,
     #[yaserde(rename = "Genre")]
     pub genre: String,
+    /// Tags with their namespace, e.g. `female:glasses, male:shota, full color`
     #[yaserde(rename = "Tags")]
     pub tags: String,
-    /// Normal chapter number
-    #[yaserde(rename = "Number")]
-    pub number: Option<String>,
-    /// Volume number
-    #[yaserde(rename = "Volume")]
-    pub volume: Option<String>,
-    /// if the value is `Special`, the chapter will be treated as a special issue by Kavita
-    #[yaserde(rename = "Format")]
-    pub format: Option<String>,
+    /// Link to the gallery, also used to detect the gallery ID when importing
+    #[yaserde(rename = "Web")]
+    pub web: Option<String>,
     /// The number of pages in this chapter
     #[yaserde(rename = "PageCount")]
     pub page_count: i64,
-    /// Total number of chapters
-    /// - `0` => Ongoing
-    /// - `Non-zero` and consistent with `Number` or `Volume` => Completed
-    /// - `Other non-zero values` => Ended
-    #[yaserde(rename = "Count")]
-    pub count: i64,
+    #[yaserde(rename = "LanguageISO")]
+    pub language_iso: Option<String>,
+    /// if the value is `Special`, the chapter will be treated as a special issue by Kavita
+    #[yaserde(rename = "Format")]
+    pub format: Option<String>,
+    #[yaserde(rename = "BlackAndWhite")]
+    pub black_and_white: Option<String>,
+    #[yaserde(rename = "Manga")]
+    pub manga: String,
+    #[yaserde(rename = "Characters")]
+    pub characters: Option<String>,
+    /// The circles (groups) the gallery is made by
+    #[yaserde(rename = "Teams")]
+    pub teams: Option<String>,
+    /// Hitomi has no locations, the parodied series are the closest match and can be browsed the same way
+    #[yaserde(rename = "Locations")]
+    pub locations: Option<String>,
+    #[yaserde(rename = "ScanInformation")]
+    pub scan_information: Option<String>,
+    #[yaserde(rename = "StoryArc")]
+    pub story_arc: Option<String>,
+    #[yaserde(rename = "StoryArcNumber")]
+    pub story_arc_number: Option<String>,
+    #[yaserde(rename = "SeriesGroup")]
+    pub series_group: Option<String>,
+    #[yaserde(rename = "AgeRating")]
+    pub age_rating: Option<String>,
+    #[yaserde(rename = "Pages")]
+    pub pages: ComicPages,
+    #[yaserde(rename = "CommunityRating")]
+    pub community_rating: Option<f32>,
+    #[yaserde(rename = "MainCharacterOrTeam")]
+    pub main_character_or_team: Option<String>,
+    #[yaserde(rename = "Review")]
+    pub review: Option<String>,
+    #[yaserde(rename = "GTIN")]
+    pub gtin: Option<String>,
+}
+
+#[derive(
+    Default, Debug, Clone, PartialEq, Serialize, Deserialize, Type, YaSerialize, YaDeserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicPages {
+    #[yaserde(rename = "Page")]
+    pub pages: Vec<ComicPageInfo>,
+}
+
+/// A `<Page>` entry, every field is an attribute
+#[derive(
+    Default, Debug, Clone, PartialEq, Serialize, Deserialize, Type, YaSerialize, YaDeserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicPageInfo {
+    /// 0-based page index
+    #[yaserde(attribute = true, rename = "Image")]
+    pub image: i32,
+    /// `FrontCover`, `Story`, etc. Readers treat pages without a type as `Story`
+    #[yaserde(attribute = true, rename = "Type")]
+    pub page_type: Option<String>,
+    #[yaserde(attribute = true, rename = "DoublePage")]
+    pub double_page: Option<bool>,
+    #[yaserde(attribute = true, rename = "ImageSize")]
+    pub image_size: Option<i64>,
+    #[yaserde(attribute = true, rename = "Key")]
+    pub key: Option<String>,
+    /// Readers show bookmarks as a table of contents
+    #[yaserde(attribute = true, rename = "Bookmark")]
+    pub bookmark: Option<String>,
+    #[yaserde(attribute = true, rename = "ImageWidth")]
+    pub image_width: Option<i32>,
+    #[yaserde(attribute = true, rename = "ImageHeight")]
+    pub image_height: Option<i32>,
+}
+
+impl ComicInfo {
+    /// Serialize to formatted xml
+    pub fn to_xml(&self) -> anyhow::Result<String> {
+        let cfg = yaserde::ser::Config {
+            perform_indent: true,
+            ..Default::default()
+        };
+        yaserde::ser::to_string_with_config(self, &cfg)
+            .map_err(|err_msg| anyhow!("Failed to serialize `ComicInfo.xml`: {err_msg}"))
+    }
 }
 
 impl From<Comic> for ComicInfo {
     fn from(comic: Comic) -> Self {
+        let date = comic.parse_date();
+        let web = comic.gallery_url();
+        let language_iso = comic.language_iso().map(ToString::to_string);
+        let pages = ComicPages::from(&comic);
+
         ComicInfo {
-            manga: "Yes".to_string(),
+            title: Some(comic.title.clone()),
             series: comic.title,
+            number: Some("1".to_string()),
+            count: 1,
+            alternate_series: Some(comic.japanese_title).filter(|title| !title.is_empty()),
+            year: date.map(|date| date.year()),
+            month: date.map(|date| date.month()),
+            day: date.map(|date| date.day()),
             writer: comic.artists.join(", "),
             publisher: "Hitomi".to_string(),
             genre: comic.type_field,
             tags: comic
                 .tags
-                .into_iter()
-                .map(|tag| tag.tag)
+                .iter()
+                .map(Tag::name_with_namespace)
                 .collect::<Vec<String>>()
                 .join(", "),
-            number: Some("1".to_string()),
-            volume: None,
-            format: Some("Special".to_string()),
+            web: Some(web),
             #[allow(clippy::cast_possible_wrap)]
             page_count: comic.files.len() as i64,
-            count: 1,
+            language_iso,
+            format: Some("Special".to_string()),
+            manga: "Yes".to_string(),
+            characters: join_non_empty(&comic.characters),
+            teams: join_non_empty(&comic.groups),
+            locations: join_non_empty(&comic.parodys),
+            age_rating: Some("Adults Only 18+".to_string()),
+            pages,
+            ..Default::default()
         }
     }
 }
+
+impl From<&Comic> for ComicPages {
+    /// One entry per file of the gallery, the first page is the cover and the first page of every scene is bookmarked.
+    /// Files that are wider than they are tall are marked as double pages
+    fn from(comic: &Comic) -> Self {
+        let pages = comic
+            .files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| {
+                #[allow(clippy::cast_possible_truncation)]
+                #[allow(clippy::cast_possible_wrap)]
+                let image = i as i32;
+                let bookmark = comic
+                    .scene_indexes
+                    .iter()
+                    .position(|scene_index| *scene_index == image)
+                    .map(|scene| format!("Scene {}", scene + 1));
+                ComicPageInfo {
+                    image,
+                    page_type: (i == 0).then(|| "FrontCover".to_string()),
+                    double_page: (file.width > file.height).then_some(true),
+                    bookmark,
+                    image_width: Some(file.width),
+                    image_height: Some(file.height),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        ComicPages { pages }
+    }
+}
+
+fn join_non_empty(values: &[String]) -> Option<String> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitomi::GalleryFiles;
+
+    fn gallery_file(name: &str, width: i32, height: i32) -> GalleryFiles {
+        GalleryFiles {
+            width,
+            hash: String::new(),
+            haswebp: 1,
+            hasavif: 1,
+            hasjxl: 0,
+            name: name.to_string(),
+            height,
+        }
+    }
+
+    fn comic() -> Comic {
+        Comic {
+            id: 123_456,
+            title: "Title & <Subtitle>".to_string(),
+            japanese_title: "タイトル".to_string(),
+            language: "japanese".to_string(),
+            type_field: "doujinshi".to_string(),
+            date: "2024-03-05 12:34:00-05".to_string(),
+            artists: vec!["artist a".to_string(), "artist b".to_string()],
+            groups: vec!["circle".to_string()],
+            parodys: vec!["original".to_string()],
+            tags: vec![
+                Tag {
+                    tag: "glasses".to_string(),
+                    female: 1,
+                    male: 0,
+                },
+                Tag {
+                    tag: "full color".to_string(),
+                    female: 0,
+                    male: 0,
+                },
+            ],
+            characters: vec!["character".to_string()],
+            scene_indexes: vec![0, 2],
+            files: vec![
+                gallery_file("1.jpg", 1280, 1810),
+                gallery_file("2.jpg", 1280, 1810),
+                gallery_file("3.jpg", 2560, 1810),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn maps_comic_fields() {
+        let comic_info = ComicInfo::from(comic());
+
+        assert_eq!(comic_info.year, Some(2024));
+        assert_eq!(comic_info.month, Some(3));
+        assert_eq!(comic_info.day, Some(5));
+        assert_eq!(comic_info.language_iso.as_deref(), Some("ja"));
+        assert_eq!(comic_info.tags, "female:glasses, full color");
+        assert_eq!(comic_info.teams.as_deref(), Some("circle"));
+        assert_eq!(
+            comic_info.web.as_deref(),
+            Some("https://hitomi.la/galleries/123456.html")
+        );
+
+        let pages = &comic_info.pages.pages;
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[0].page_type.as_deref(), Some("FrontCover"));
+        assert_eq!(pages[1].page_type, None);
+        assert_eq!(pages[2].bookmark.as_deref(), Some("Scene 2"));
+        assert_eq!(pages[2].image_width, Some(2560));
+        assert_eq!(pages[0].double_page, None);
+        assert_eq!(pages[2].double_page, Some(true));
+    }
+
+    #[test]
+    fn round_trips_through_xml() {
+        let comic_info = ComicInfo::from(comic());
+        let xml = comic_info.to_xml().unwrap();
+        let deserialized: ComicInfo = yaserde::de::from_str(&xml).unwrap();
+
+        assert_eq!(deserialized, comic_info);
+    }
+}
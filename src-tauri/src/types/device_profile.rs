@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// How pages are prepared for a reading device during export, similar to the profiles of Kindle Comic Converter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceProfile {
+    pub name: String,
+    /// Screen width in pixels, pages are scaled down to fit the screen but never scaled up
+    pub width: u32,
+    /// Screen height in pixels
+    pub height: u32,
+    pub grayscale: bool,
+    /// Passed to `image::imageops::contrast`, positive values increase contrast, `0` keeps it as it is
+    pub contrast: f32,
+    /// Values above `1` darken the mid-tones, which makes thin lines more visible on e-ink, `1` keeps them as they are
+    pub gamma: f32,
+    pub image_format: DeviceImageFormat,
+    /// 1-100, only used when `image_format` is `DeviceImageFormat::Jpeg`
+    pub jpeg_quality: u8,
+    /// Crop the uniform white or black margins around the content before scaling
+    pub crop_margins: bool,
+}
+
+/// The format pages are encoded in after being processed with a `DeviceProfile`
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum DeviceImageFormat {
+    #[default]
+    Jpeg,
+    /// Lossless, larger than JPEG but without compression artifacts on line art
+    Png,
+}
+
+impl DeviceImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            DeviceImageFormat::Jpeg => "jpg",
+            DeviceImageFormat::Png => "png",
+        }
+    }
+}
+
+impl DeviceProfile {
+    /// Profiles of common e-readers, the settings can be adjusted after choosing one
+    pub fn presets() -> Vec<DeviceProfile> {
+        let e_ink = |name: &str, width: u32, height: u32| DeviceProfile {
+            name: name.to_string(),
+            width,
+            height,
+            grayscale: true,
+            contrast: 10.0,
+            gamma: 1.8,
+            image_format: DeviceImageFormat::Jpeg,
+            jpeg_quality: 85,
+            crop_margins: true,
+        };
+        vec![
+            e_ink("Kindle Paperwhite 5 / Signature Edition", 1236, 1648),
+            e_ink("Kindle Oasis 2/3", 1264, 1680),
+            e_ink("Kindle Scribe", 1860, 2480),
+            e_ink("Kobo Clara 2E / BW", 1072, 1448),
+            e_ink("Kobo Libra 2 / H2O", 1264, 1680),
+            e_ink("Kobo Elipsa / Sage", 1404, 1872),
+            DeviceProfile {
+                grayscale: false,
+                gamma: 1.0,
+                contrast: 0.0,
+                ..e_ink("Kobo Libra Colour", 1264, 1680)
+            },
+        ]
+    }
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// What a batch export does with comics that were already exported to the same format
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum ExistingExportPolicy {
+    /// Keep the existing file and don't export the comic again
+    Skip,
+    /// Keep the existing file if the comic hasn't changed since it was exported, otherwise export it again
+    #[default]
+    SkipUpToDate,
+    Overwrite,
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// The file formats comics can be exported to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+pub enum ExportFormat {
+    Cbz,
+    Pdf,
+    Epub,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Cbz, ExportFormat::Pdf, ExportFormat::Epub];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Cbz => "cbz",
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Epub => "epub",
+        }
+    }
+}
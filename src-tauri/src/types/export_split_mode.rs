@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// How CBZ and PDF exports are split into several volumes
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum ExportSplitMode {
+    /// One file per comic
+    #[default]
+    None,
+    /// A new volume starts at every entry of `Comic::scene_indexes`
+    Scenes,
+    /// A new volume starts every `Config::export_pages_per_volume` pages
+    PageCount,
+}
@@ -1,13 +1,35 @@
+mod cbz_compression;
 mod comic;
 mod comic_info;
+mod device_profile;
 mod download_format;
+mod existing_export_policy;
+mod export_format;
+mod export_split_mode;
 mod log_level;
+mod pdf_image_encoding;
 mod proxy_mode;
+mod reading_direction;
 mod search_result;
+mod sidecar_format;
+mod spread_handling;
+mod storage_mode;
+mod sync_target;
 
+pub use cbz_compression::*;
 pub use comic::*;
 pub use comic_info::*;
+pub use device_profile::*;
 pub use download_format::*;
+pub use existing_export_policy::*;
+pub use export_format::*;
+pub use export_split_mode::*;
 pub use log_level::*;
+pub use pdf_image_encoding::*;
 pub use proxy_mode::*;
+pub use reading_direction::*;
 pub use search_result::*;
+pub use sidecar_format::*;
+pub use spread_handling::*;
+pub use storage_mode::*;
+pub use sync_target::*;
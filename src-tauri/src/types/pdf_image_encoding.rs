@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// How pages that PDF can't embed as they are, such as AVIF and JPEG XL, are transcoded during PDF export
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum PdfImageEncoding {
+    /// Lossy, with the quality of `Config::pdf_jpeg_quality`
+    #[default]
+    Jpeg,
+    /// Lossless, but the file is usually several times larger
+    Flate,
+}
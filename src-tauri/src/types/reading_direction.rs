@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// Page progression of exported comics, readers that support it turn pages in this direction
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum ReadingDirection {
+    #[default]
+    LeftToRight,
+    /// Used by most manga
+    RightToLeft,
+}
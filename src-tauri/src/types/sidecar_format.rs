@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// Metadata formats of other tools that can be written next to `metadata.json`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum SidecarFormat {
+    /// `ComicInfo.xml` read by Komga, Kavita and most comic readers
+    ComicInfoXml,
+    /// `info.json` written by gallery-dl with `--write-info-json`
+    GalleryDlJson,
+    /// `info.txt` written by HDoujin Downloader
+    HDoujinTxt,
+}
+
+impl SidecarFormat {
+    pub const ALL: [SidecarFormat; 3] = [
+        SidecarFormat::ComicInfoXml,
+        SidecarFormat::GalleryDlJson,
+        SidecarFormat::HDoujinTxt,
+    ];
+
+    pub fn filename(self) -> &'static str {
+        match self {
+            SidecarFormat::ComicInfoXml => "ComicInfo.xml",
+            SidecarFormat::GalleryDlJson => "info.json",
+            SidecarFormat::HDoujinTxt => "info.txt",
+        }
+    }
+}
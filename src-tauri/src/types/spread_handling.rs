@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// What happens to double-page spreads during export, a page that is wider than it is tall is a spread
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum SpreadHandling {
+    /// Export spreads as they are, marked as `DoublePage` in `ComicInfo.xml`
+    #[default]
+    Keep,
+    /// Split spreads into two pages, in the order of `Config::reading_direction`
+    Split,
+    /// Rotate spreads to fill a portrait screen, the half that is read first ends up at the top
+    Rotate,
+}
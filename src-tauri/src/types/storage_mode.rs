@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// How downloaded pages are stored in `Config::download_dir`
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum StorageMode {
+    /// Every comic folder holds its own copy of its pages
+    #[default]
+    Folder,
+    /// Pages are stored once by content hash in the page store and hardlinked into the comic folders,
+    /// so byte-identical pages of different galleries take up disk space only once
+    DeduplicatedFolder,
+    /// Every comic is packed into a CBZ archive with `metadata.json` and `ComicInfo.xml` inside,
+    /// the archive takes the place of the comic folder
+    Cbz,
+}
@@ -0,0 +1,14 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// A directory that `export_dir` is mirrored to, such as a mounted e-reader or a NAS share
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncTarget {
+    pub path: PathBuf,
+    /// Delete files that an earlier sync copied to the target but that are no longer in `export_dir`,
+    /// files that weren't copied by a sync are never touched
+    pub delete_removed: bool,
+}
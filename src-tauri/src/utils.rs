@@ -1,5 +1,8 @@
-use std::sync::OnceLock;
+use std::{io::ErrorKind, path::Path, sync::OnceLock};
+
+use anyhow::{anyhow, Context};
 use tauri::AppHandle;
+use walkdir::WalkDir;
 
 pub fn filename_filter(s: &str) -> String {
     s.chars()
@@ -29,3 +32,65 @@ pub fn get_app_handle() -> AppHandle {
         .expect("APP_HANDLE not initialized")
         .clone()
 }
+
+/// Move `from` to `to`, creating the parent directory of `to` if needed
+///
+/// `to` must not exist yet, so that a directory is never merged into another one.
+/// Falls back to copy and delete if `from` and `to` are on different file systems
+pub fn move_path(from: &Path, to: &Path) -> anyhow::Result<()> {
+    if to.exists() {
+        return Err(anyhow!("`{}` already exists", to.display()));
+    }
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)
+            .context(format!("Failed to create directory `{}`", parent.display()))?;
+    }
+
+    match std::fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => {}
+        Err(err) => {
+            return Err(anyhow!(err).context(format!(
+                "Failed to rename `{}` to `{}`",
+                from.display(),
+                to.display()
+            )))
+        }
+    }
+    // `rename` does not work across file systems, so copy first and then delete
+    if from.is_dir() {
+        for entry in WalkDir::new(from) {
+            let entry = entry.context(format!("Failed to traverse `{}`", from.display()))?;
+            let relative_path = entry.path().strip_prefix(from).context(format!(
+                "Failed to strip prefix `{}` from `{}`",
+                from.display(),
+                entry.path().display()
+            ))?;
+            let target_path = to.join(relative_path);
+            if entry.file_type().is_dir() {
+                std::fs::create_dir_all(&target_path).context(format!(
+                    "Failed to create directory `{}`",
+                    target_path.display()
+                ))?;
+            } else {
+                std::fs::copy(entry.path(), &target_path).context(format!(
+                    "Failed to copy `{}` to `{}`",
+                    entry.path().display(),
+                    target_path.display()
+                ))?;
+            }
+        }
+        std::fs::remove_dir_all(from)
+            .context(format!("Failed to delete directory `{}`", from.display()))?;
+    } else {
+        std::fs::copy(from, to).context(format!(
+            "Failed to copy `{}` to `{}`",
+            from.display(),
+            to.display()
+        ))?;
+        std::fs::remove_file(from)
+            .context(format!("Failed to delete file `{}`", from.display()))?;
+    }
+
+    Ok(())
+}
@@ -94,6 +94,39 @@ async exportCbz(comic: Comic) : Promise<Result<null, CommandError>> {
     else return { status: "error", error: e  as any };
 }
 },
+async exportEpub(comic: Comic) : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("export_epub", { comic }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async exportComics(comics: Comic[], formats: ExportFormat[]) : Promise<ExportBatchReport> {
+    return await TAURI_INVOKE("export_comics", { comics, formats });
+},
+async getExportStatusReport(formats: ExportFormat[]) : Promise<Result<ExportStatusReport, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_export_status_report", { formats }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async cancelExport(uuid: string) : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("cancel_export", { uuid }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getDeviceProfilePresets() : Promise<DeviceProfile[]> {
+    return await TAURI_INVOKE("get_device_profile_presets");
+},
+async syncExports() : Promise<SyncReport[]> {
+    return await TAURI_INVOKE("sync_exports");
+},
 async getSearchSuggestions(query: string) : Promise<Result<Suggestion[], CommandError>> {
     try {
     return { status: "ok", data: await TAURI_INVOKE("get_search_suggestions", { query }) };
@@ -133,6 +166,80 @@ async getSyncedComic(comic: Comic) : Promise<Result<Comic, CommandError>> {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
 }
+},
+async deleteDownloadedComics(ids: number[], deleteExports: boolean) : Promise<TrashReport> {
+    return await TAURI_INVOKE("delete_downloaded_comics", { ids, deleteExports });
+},
+async getTrashedComics() : Promise<Result<TrashedComic[], CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_trashed_comics") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async restoreTrashedComics(trashIds: string[]) : Promise<Result<Comic[], CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("restore_trashed_comics", { trashIds }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async deleteTrashedComics(trashIds: string[]) : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("delete_trashed_comics", { trashIds }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getLibraryStats(topN: number) : Promise<LibraryStats> {
+    return await TAURI_INVOKE("get_library_stats", { topN });
+},
+async auditLibrary(decodeImages: boolean) : Promise<AuditReport> {
+    return await TAURI_INVOKE("audit_library", { decodeImages });
+},
+async findDuplicateComics(minSimilarity: number) : Promise<Result<DuplicateGroup[], CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("find_duplicate_comics", { minSimilarity }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async collectPageStoreGarbage() : Promise<Result<PageStoreGcReport, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("collect_page_store_garbage") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async verifyLibrary(ids: number[] | null) : Promise<VerifyReport> {
+    return await TAURI_INVOKE("verify_library", { ids });
+},
+async verifyExportedCbzs(ids: number[] | null) : Promise<VerifyReport> {
+    return await TAURI_INVOKE("verify_exported_cbzs", { ids });
+},
+async importComics(sourceDir: string, mode: ImportMode) : Promise<Result<ImportReport, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("import_comics", { sourceDir, mode }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async refreshComicsMetadata(ids: number[] | null) : Promise<RefreshReport> {
+    return await TAURI_INVOKE("refresh_comics_metadata", { ids });
+},
+async writeSidecars(ids: number[] | null, formats: SidecarFormat[]) : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("write_sidecars", { ids, formats }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
 }
 }
 
@@ -142,15 +249,25 @@ async getSyncedComic(comic: Comic) : Promise<Result<Comic, CommandError>> {
 export const events = __makeEvents__<{
 downloadSpeedEvent: DownloadSpeedEvent,
 downloadTaskEvent: DownloadTaskEvent,
+exportBatchEvent: ExportBatchEvent,
 exportCbzEvent: ExportCbzEvent,
+exportEpubEvent: ExportEpubEvent,
 exportPdfEvent: ExportPdfEvent,
-logEvent: LogEvent
+libraryChangedEvent: LibraryChangedEvent,
+logEvent: LogEvent,
+refreshMetadataEvent: RefreshMetadataEvent,
+syncExportsEvent: SyncExportsEvent
 }>({
 downloadSpeedEvent: "download-speed-event",
 downloadTaskEvent: "download-task-event",
+exportBatchEvent: "export-batch-event",
 exportCbzEvent: "export-cbz-event",
+exportEpubEvent: "export-epub-event",
 exportPdfEvent: "export-pdf-event",
-logEvent: "log-event"
+libraryChangedEvent: "library-changed-event",
+logEvent: "log-event",
+refreshMetadataEvent: "refresh-metadata-event",
+syncExportsEvent: "sync-exports-event"
 })
 
 /** user-defined constants **/
@@ -159,24 +276,413 @@ logEvent: "log-event"
 
 /** user-defined types **/
 
-export type Comic = { id: number; title: string; japaneseTitle: string; language: string; languageLocalname: string; type: string; date: string; artists: string[]; groups: string[]; parodys: string[]; tags: Tag[]; related: number[]; languages: Language[]; characters: string[]; sceneIndexes: number[]; files: GalleryFiles[]; coverUrl: string; isDownloaded?: boolean | null; comicDownloadDir?: string | null }
+export type AuditReport = { auditedCount: number; 
+/**
+ * Only comics with at least one issue are included
+ */
+comicAudits: ComicAudit[] }
+/**
+ * How images are compressed in exported CBZ files
+ */
+export type CbzCompression = 
+/**
+ * Images are already compressed, storing them is faster and the archive is barely larger
+ */
+"Store" | "Deflate"
+export type Comic = { id: number; title: string; japaneseTitle: string; language: string; languageLocalname: string; type: string; date: string; artists: string[]; groups: string[]; parodys: string[]; tags: Tag[]; related: number[]; languages: Language[]; characters: string[]; sceneIndexes: number[]; files: GalleryFiles[]; coverUrl: string; 
+/**
+ * The format the images were downloaded in, only recorded in the metadata file
+ */
+downloadFormat?: DownloadFormat | null; 
+/**
+ * Whether the images were deleted after auto export on purpose, only the metadata files are left in that case
+ */
+imagesDeleted?: boolean | null; isDownloaded?: boolean | null; comicDownloadDir?: string | null }
+export type ComicAudit = { id: number; title: string; metadataPath: string; 
+/**
+ * Set if `metadata.json` can't be converted to `Comic`, other fields are meaningless in this case
+ */
+metadataError: string | null; expectedPageCount: number; pageCount: number; 
+/**
+ * 1-based page numbers that have no image file
+ */
+missingPages: number[]; 
+/**
+ * Filenames of pages that are corrupted or whose content doesn't match their extension
+ */
+brokenPages: string[]; 
+/**
+ * Filenames of pages whose extension doesn't match the recorded download format
+ */
+mismatchedFormatPages: string[]; 
+/**
+ * Filenames of files that don't belong to the comic
+ */
+strayFiles: string[]; repairActions: RepairAction[]; 
+/**
+ * Set if the files of the comic can't be read at all, the page fields are empty in this case
+ */
+error: string | null }
+export type ComicSimilarity = { id: number; otherId: number; 
+/**
+ * Share of the pages of both comics that have a match in the other one, from 0 to 1
+ */
+similarity: number }
+export type ComicSize = { id: number; title: string; comicDownloadDir: string; size: number; pageCount: number }
+export type ComicVerification = { id: number; title: string; 
+/**
+ * The comic download directory, or the exported CBZ file
+ */
+path: string; 
+/**
+ * Set if the comic can't be verified at all, such as when it has no manifest, other fields are empty in this case
+ */
+error: string | null; 
+/**
+ * Files whose content doesn't match the manifest
+ */
+mismatchedFiles: string[]; 
+/**
+ * Files in the manifest that don't exist
+ */
+missingFiles: string[]; 
+/**
+ * Pages that are not in the manifest
+ */
+extraFiles: string[] }
 export type CommandError = { err_title: string; err_message: string }
-export type Config = { downloadDir: string; exportDir: string; enableFileLogger: boolean; downloadFormat: DownloadFormat; dirFmt: string; proxyHost: string; proxyMode: ProxyMode; proxyPort: number }
+export type Config = { downloadDir: string; exportDir: string; enableFileLogger: boolean; downloadFormat: DownloadFormat; dirFmt: string; proxyHost: string; proxyMode: ProxyMode; proxyPort: number; sidecarFormats: SidecarFormat[]; storageMode: StorageMode; readingDirection: ReadingDirection; pdfImageEncoding: PdfImageEncoding; 
+/**
+ * 1-100, only used when `pdf_image_encoding` is `PdfImageEncoding::Jpeg`
+ */
+pdfJpegQuality: number; cbzCompression: CbzCompression; 
+/**
+ * Only CBZ and PDF exports are split, an EPUB is always one file
+ */
+exportSplitMode: ExportSplitMode; 
+/**
+ * Only used when `export_split_mode` is `ExportSplitMode::PageCount`
+ */
+exportPagesPerVolume: number; 
+/**
+ * Pages are processed for this device during CBZ, PDF and EPUB export, `None` exports them as they are
+ */
+deviceProfile: DeviceProfile | null; 
+/**
+ * How CBZ, PDF and EPUB exports handle pages that are wider than they are tall
+ */
+spreadHandling: SpreadHandling; 
+/**
+ * Directories `sync_exports` mirrors `export_dir` to
+ */
+syncTargets: SyncTarget[]; 
+/**
+ * The number of comics `export_comics` exports at the same time
+ */
+exportConcurrency: number; existingExportPolicy: ExistingExportPolicy; 
+/**
+ * Formats every comic is exported to once its download completes, empty to not export automatically
+ */
+autoExportFormats: ExportFormat[]; 
+/**
+ * Delete the downloaded images once all automatic exports succeed, `metadata.json` is kept.
+ * Ignored with `StorageMode::Cbz`, where the images are in the archive
+ */
+deleteImagesAfterAutoExport: boolean }
+/**
+ * The format pages are encoded in after being processed with a `DeviceProfile`
+ */
+export type DeviceImageFormat = "Jpeg" | 
+/**
+ * Lossless, larger than JPEG but without compression artifacts on line art
+ */
+"Png"
+/**
+ * How pages are prepared for a reading device during export, similar to the profiles of Kindle Comic Converter
+ */
+export type DeviceProfile = { name: string; 
+/**
+ * Screen width in pixels, pages are scaled down to fit the screen but never scaled up
+ */
+width: number; 
+/**
+ * Screen height in pixels
+ */
+height: number; grayscale: boolean; 
+/**
+ * Passed to `image::imageops::contrast`, positive values increase contrast, `0` keeps it as it is
+ */
+contrast: number; 
+/**
+ * Values above `1` darken the mid-tones, which makes thin lines more visible on e-ink, `1` keeps them as they are
+ */
+gamma: number; imageFormat: DeviceImageFormat; 
+/**
+ * 1-100, only used when `image_format` is `DeviceImageFormat::Jpeg`
+ */
+jpegQuality: number; 
+/**
+ * Crop the uniform white or black margins around the content before scaling
+ */
+cropMargins: boolean }
 export type DownloadFormat = "Webp" | "Avif"
 export type DownloadSpeedEvent = { speed: string }
 export type DownloadTaskEvent = { event: "Create"; data: { state: DownloadTaskState; comic: Comic; downloadedImgCount: number; totalImgCount: number } } | { event: "Update"; data: { comicId: number; state: DownloadTaskState; downloadedImgCount: number; totalImgCount: number } }
-export type DownloadTaskState = "Pending" | "Downloading" | "Paused" | "Cancelled" | "Completed" | "Failed"
-export type ExportCbzEvent = { event: "Start"; data: { uuid: string; title: string } } | { event: "Error"; data: { uuid: string } } | { event: "End"; data: { uuid: string } }
-export type ExportPdfEvent = { event: "Start"; data: { uuid: string; title: string } } | { event: "Error"; data: { uuid: string } } | { event: "End"; data: { uuid: string } }
+export type DownloadTaskState = "Pending" | "Downloading" | 
+/**
+ * All images are downloaded and the comic is being exported to `Config::auto_export_formats`
+ */
+"Exporting" | "Paused" | "Cancelled" | "Completed" | "Failed"
+export type DuplicateComic = { id: number; title: string; language: string; comicDownloadDir: string; pageCount: number; size: number }
+export type DuplicateGroup = { comics: DuplicateComic[]; 
+/**
+ * Similarity of every pair of comics in the group that reached `min_similarity`
+ */
+similarities: ComicSimilarity[]; 
+/**
+ * The copy with the most pages, then the largest size, then the smallest ID
+ */
+suggestedKeepId: number }
+/**
+ * What a batch export does with comics that were already exported to the same format
+ */
+export type ExistingExportPolicy = 
+/**
+ * Keep the existing file and don't export the comic again
+ */
+"Skip" | 
+/**
+ * Keep the existing file if the comic hasn't changed since it was exported, otherwise export it again
+ */
+"SkipUpToDate" | "Overwrite"
+export type ExportBatchEvent = { event: "Start"; data: { uuid: string; total: number } } | { event: "Progress"; data: { uuid: string; current: number; total: number; failed: number } } | { event: "End"; data: { uuid: string } }
+export type ExportBatchReport = { exported: ExportJob[]; 
+/**
+ * Jobs whose output already existed, or was up to date with `ExistingExportPolicy::SkipUpToDate`
+ */
+skipped: ExportJob[]; failed: FailedExport[] }
+export type ExportCbzEvent = { event: "Start"; data: { uuid: string; title: string } } | { event: "Progress"; data: { uuid: string; current: number; total: number } } | { event: "Error"; data: { uuid: string } } | { event: "Cancelled"; data: { uuid: string } } | { event: "End"; data: { uuid: string } }
+export type ExportEpubEvent = { event: "Start"; data: { uuid: string; title: string } } | { event: "Progress"; data: { uuid: string; current: number; total: number } } | { event: "Error"; data: { uuid: string } } | { event: "Cancelled"; data: { uuid: string } } | { event: "End"; data: { uuid: string } }
+/**
+ * The file formats comics can be exported to
+ */
+export type ExportFormat = "Cbz" | "Pdf" | "Epub"
+/**
+ * One comic exported to one format
+ */
+export type ExportJob = { id: number; title: string; format: ExportFormat }
+export type ExportPdfEvent = { event: "Start"; data: { uuid: string; title: string } } | { event: "Progress"; data: { uuid: string; current: number; total: number } } | { event: "Error"; data: { uuid: string } } | { event: "Cancelled"; data: { uuid: string } } | { event: "End"; data: { uuid: string } }
+/**
+ * How CBZ and PDF exports are split into several volumes
+ */
+export type ExportSplitMode = 
+/**
+ * One file per comic
+ */
+"None" | 
+/**
+ * A new volume starts at every entry of `Comic::scene_indexes`
+ */
+"Scenes" | 
+/**
+ * A new volume starts every `Config::export_pages_per_volume` pages
+ */
+"PageCount"
+export type ExportStatusEntry = { id: number; title: string; format: ExportFormat; 
+/**
+ * More than one if the export is split into volumes
+ */
+paths: string[] }
+export type ExportStatusReport = { missing: ExportStatusEntry[]; stale: ExportStatusEntry[]; 
+/**
+ * Exports and source manifests in `export_dir` that don't belong to any downloaded comic
+ */
+orphaned: string[]; 
+/**
+ * Comics whose exports couldn't be checked, the other comics are still checked
+ */
+failed: FailedExportStatus[] }
+export type FailedExport = { id: number; title: string; format: ExportFormat; reason: string }
+export type FailedExportStatus = { id: number; title: string; reason: string }
+export type FailedRefresh = { id: number; title: string; reason: string }
+export type FailedSync = { path: string; reason: string }
+export type FailedTrash = { id: number; title: string; reason: string }
 export type GalleryFiles = { width: number; hash: string; haswebp?: number; hasavif?: number; hasjxl?: number; name: string; height: number }
+export type ImportMode = 
+/**
+ * Move the pages into the library, the source pages and `.cbz` files are deleted once the comic is stored
+ */
+"Move" | 
+/**
+ * Copy the pages into the library, leaving the source untouched
+ */
+"Copy"
+export type ImportReport = { imported: ImportedComic[]; skipped: SkippedImport[] }
+export type ImportedComic = { sourcePath: string; comic: Comic }
 export type JsonValue = null | boolean | number | string | JsonValue[] | { [key in string]: JsonValue }
 export type Language = { galleryid: number; language_localname: string; name: string }
+export type LeftoverDir = { path: string; size: number }
+/**
+ * Comics were added to, removed from or had their metadata edited in the download directory outside of the app
+ */
+export type LibraryChangedEvent = { addedIds: number[]; removedIds: number[]; updatedIds: number[] }
+export type LibraryStats = { comicCount: number; 
+/**
+ * Sum of the sizes of the comics, a page shared through `StorageMode::DeduplicatedFolder` counts in every comic that links it
+ */
+totalSize: number; 
+/**
+ * Space that `download_dir` takes on disk, hardlinked files are counted once and the page store is included
+ */
+diskSize: number; pageCount: number; byFormat: StatsEntry[]; byArtist: StatsEntry[]; byGroup: StatsEntry[]; byTag: StatsEntry[]; byLanguage: StatsEntry[]; byType: StatsEntry[]; 
+/**
+ * Grouped by the month (`YYYY-MM`) in which `metadata.json` was last modified, oldest first
+ */
+byMonth: StatsEntry[]; largestComics: ComicSize[]; 
+/**
+ * Leftover `.downloading-*` directories of unfinished or cancelled download tasks
+ */
+leftoverDirs: LeftoverDir[]; leftoverSize: number }
 export type LogEvent = { timestamp: string; level: LogLevel; fields: { [key in string]: JsonValue }; target: string; filename: string; line_number: number }
 export type LogLevel = "TRACE" | "DEBUG" | "INFO" | "WARN" | "ERROR"
+export type PageStoreGcReport = { 
+/**
+ * Number of blobs left in the page store after the collection
+ */
+blobCount: number; removedCount: number; reclaimedSize: number }
+/**
+ * How pages that PDF can't embed as they are, such as AVIF and JPEG XL, are transcoded during PDF export
+ */
+export type PdfImageEncoding = 
+/**
+ * Lossy, with the quality of `Config::pdf_jpeg_quality`
+ */
+"Jpeg" | 
+/**
+ * Lossless, but the file is usually several times larger
+ */
+"Flate"
 export type ProxyMode = "System" | "NoProxy" | "Custom"
+/**
+ * Page progression of exported comics, readers that support it turn pages in this direction
+ */
+export type ReadingDirection = "LeftToRight" | 
+/**
+ * Used by most manga
+ */
+"RightToLeft"
+export type RefreshMetadataEvent = { event: "Start"; data: { uuid: string; total: number } } | { event: "Progress"; data: { uuid: string; current: number; total: number } } | { event: "End"; data: { uuid: string } }
+export type RefreshReport = { refreshed: RefreshedComic[]; failed: FailedRefresh[] }
+export type RefreshedComic = { id: number; title: string; 
+/**
+ * Names of the `Comic` fields that changed on the site, empty if nothing changed
+ */
+changedFields: string[] }
+export type RepairAction = 
+/**
+ * Download the missing or broken pages again
+ */
+"RedownloadPages" | 
+/**
+ * Scan the download directory again to bring `metadata.json` and the files back in line
+ */
+"Rescan" | 
+/**
+ * Nothing usable is left in the download directory, remove it from the library
+ */
+"DropEntry"
 export type SearchResult = { comics: Comic[]; currentPage: number; totalPage: number; ids: number[] }
+/**
+ * Metadata formats of other tools that can be written next to `metadata.json`
+ */
+export type SidecarFormat = 
+/**
+ * `ComicInfo.xml` read by Komga, Kavita and most comic readers
+ */
+"ComicInfoXml" | 
+/**
+ * `info.json` written by gallery-dl with `--write-info-json`
+ */
+"GalleryDlJson" | 
+/**
+ * `info.txt` written by HDoujin Downloader
+ */
+"HDoujinTxt"
+export type SkippedImport = { sourcePath: string; reason: string }
+/**
+ * What happens to double-page spreads during export, a page that is wider than it is tall is a spread
+ */
+export type SpreadHandling = 
+/**
+ * Export spreads as they are, marked as `DoublePage` in `ComicInfo.xml`
+ */
+"Keep" | 
+/**
+ * Split spreads into two pages, in the order of `Config::reading_direction`
+ */
+"Split" | 
+/**
+ * Rotate spreads to fill a portrait screen, the half that is read first ends up at the top
+ */
+"Rotate"
+export type StatsEntry = { key: string; comicCount: number; totalSize: number; pageCount: number }
+/**
+ * How downloaded pages are stored in `Config::download_dir`
+ */
+export type StorageMode = 
+/**
+ * Every comic folder holds its own copy of its pages
+ */
+"Folder" | 
+/**
+ * Pages are stored once by content hash in the page store and hardlinked into the comic folders,
+ * so byte-identical pages of different galleries take up disk space only once
+ */
+"DeduplicatedFolder" | 
+/**
+ * Every comic is packed into a CBZ archive with `metadata.json` and `ComicInfo.xml` inside,
+ * the archive takes the place of the comic folder
+ */
+"Cbz"
 export type Suggestion = { s: string; t: number; u: string; n: string }
+/**
+ * Emitted once per target by `sync_exports`, `total` is the number of files in `export_dir`
+ */
+export type SyncExportsEvent = { event: "Start"; data: { uuid: string; target: string; total: number } } | { event: "Progress"; data: { uuid: string; current: number; total: number; failed: number } } | { event: "End"; data: { uuid: string } }
+export type SyncReport = { target: string; 
+/**
+ * Paths relative to `export_dir` and the target
+ */
+copied: string[]; deleted: string[]; unchangedCount: number; failed: FailedSync[]; 
+/**
+ * Set if the target couldn't be synced at all, e.g. because the device isn't mounted
+ */
+error: string | null }
+/**
+ * A directory that `export_dir` is mirrored to, such as a mounted e-reader or a NAS share
+ */
+export type SyncTarget = { path: string; 
+/**
+ * Delete files that an earlier sync copied to the target but that are no longer in `export_dir`,
+ * files that weren't copied by a sync are never touched
+ */
+deleteRemoved: boolean }
 export type Tag = { tag: string; female: number; male: number }
+export type TrashReport = { trashed: TrashedComic[]; failed: FailedTrash[] }
+/**
+ * A comic that has been moved to the trash
+ * 
+ * Each trashed comic has its own directory under the trash directory:
+ * - `trash-info.json`: serialized `TrashedComic`
+ * - `comic`: the original `comic_download_dir`
+ * - `exports`: the export files that were trashed along with the comic
+ */
+export type TrashedComic = { trashId: string; comic: Comic; originalDownloadDir: string; originalExportPaths: string[]; trashedAt: string }
+export type VerifyReport = { verifiedCount: number; 
+/**
+ * Only comics with at least one issue are included
+ */
+comicVerifications: ComicVerification[] }
 
 /** tauri-specta globals **/
 